@@ -0,0 +1,469 @@
+use glam::Vec3;
+
+use crate::sprite::{SpriteSheet, TextureRegion};
+use crate::Engine;
+
+/// Frames shorter than this are stretched so a huge `delta_time` can't spin forever.
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+/// What happens once the last frame of an `Animation` is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaybackMode {
+    /// Back to the first frame.
+    #[default]
+    Loop,
+    /// Plays backwards to the first frame, then forwards again. Each pass in either direction
+    /// is one cycle.
+    PingPong,
+    /// Stops on the last frame.
+    Once,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationFrame {
+    pub region: TextureRegion,
+    /// In seconds.
+    pub duration: f32,
+}
+
+impl AnimationFrame {
+    pub fn new(region: TextureRegion, duration: f32) -> Self {
+        Self { region, duration }
+    }
+}
+
+/// A sequence of frames with their durations. Played by an `AnimationPlayer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    mode: PlaybackMode,
+    // cycles before stopping, `None` for forever
+    repeat: Option<u32>,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<AnimationFrame>, mode: PlaybackMode) -> Self {
+        Self {
+            frames,
+            mode,
+            repeat: None,
+        }
+    }
+
+    /// Stops a `Loop` or `PingPong` animation after `count` cycles, on the frame the last cycle
+    /// ends on. A ping-pong cycle is a single pass like in Aseprite, so odd counts end on the
+    /// last frame and even ones on the first. `Once` always plays a single cycle.
+    pub fn with_repeat(mut self, count: u32) -> Self {
+        self.repeat = Some(count);
+        self
+    }
+
+    /// Frames `indices` of `sheet`, each shown for `frame_duration` seconds.
+    /// Indices outside the sheet are skipped.
+    pub fn from_sprite_sheet(
+        sheet: &SpriteSheet,
+        indices: impl IntoIterator<Item = usize>,
+        frame_duration: f32,
+        mode: PlaybackMode,
+    ) -> Self {
+        let frames = indices
+            .into_iter()
+            .filter_map(|index| sheet.frame(index))
+            .map(|region| AnimationFrame::new(*region, frame_duration))
+            .collect();
+        Self::new(frames, mode)
+    }
+
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+    }
+
+    /// Cycles played before stopping, `None` for forever.
+    pub fn repeat(&self) -> Option<u32> {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: Option<u32>) {
+        self.repeat = repeat;
+    }
+
+    /// Duration of one pass over the frames, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+}
+
+/// Emitted by `AnimationPlayer::update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// The frame at this index was shown for its whole duration.
+    FrameFinished(usize),
+    /// The clip reached its end. Sent once per cycle for looping modes, so at both ends for
+    /// `PingPong`.
+    Finished,
+}
+
+/// Plays an `Animation`, advanced with the `delta_time` passed to `Application::on_update`.
+///
+/// ```ignore
+/// let mut player = AnimationPlayer::new(walk);
+///
+/// // on_update
+/// for event in player.update(delta_time) {
+///     if let AnimationEvent::Finished = event { /* ... */ }
+/// }
+///
+/// // on_render
+/// engine.render_animation(position, scale, 0.0, color, &player);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    animation: Animation,
+    frame: usize,
+    // time spent on the current frame
+    elapsed: f32,
+    // ping-pong direction
+    forward: bool,
+    playing: bool,
+    finished: bool,
+    // cycles finished since the last reset
+    cycles: u32,
+    speed: f32,
+    events: Vec<AnimationEvent>,
+}
+
+impl AnimationPlayer {
+    pub fn new(animation: Animation) -> Self {
+        Self {
+            animation,
+            frame: 0,
+            elapsed: 0.0,
+            forward: true,
+            playing: true,
+            finished: false,
+            cycles: 0,
+            speed: 1.0,
+            events: vec![],
+        }
+    }
+
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Swaps the animation and starts it from the beginning.
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.animation = animation;
+        self.reset();
+    }
+
+    /// Back to the first frame, playing.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.playing = true;
+        self.finished = false;
+        self.cycles = 0;
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// True once a `PlaybackMode::Once` animation, or one with a repeat count, is done.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Playback rate, 1.0 is normal speed.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Region of the frame being shown, `None` for an animation without frames.
+    pub fn current_region(&self) -> Option<&TextureRegion> {
+        self.animation
+            .frames
+            .get(self.frame)
+            .map(|frame| &frame.region)
+    }
+
+    /// Advances by `delta_time` seconds and returns what happened meanwhile.
+    pub fn update(&mut self, delta_time: f32) -> &[AnimationEvent] {
+        self.events.clear();
+        let frame_count = self.animation.frames.len();
+        if !self.playing || frame_count == 0 {
+            return &self.events;
+        }
+
+        self.elapsed += delta_time * self.speed;
+        loop {
+            let duration = self.animation.frames[self.frame]
+                .duration
+                .max(MIN_FRAME_DURATION);
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            self.events.push(AnimationEvent::FrameFinished(self.frame));
+
+            let last = frame_count - 1;
+            match self.animation.mode {
+                PlaybackMode::Loop => {
+                    if self.frame == last {
+                        if self.finish_cycle() {
+                            break;
+                        }
+                        self.frame = 0;
+                    } else {
+                        self.frame += 1;
+                    }
+                }
+                PlaybackMode::PingPong => {
+                    let end = if self.forward { last } else { 0 };
+                    if self.frame == end {
+                        if self.finish_cycle() {
+                            break;
+                        }
+                        self.forward = !self.forward;
+                    }
+                    // a single frame has nowhere to move
+                    if last > 0 {
+                        if self.forward {
+                            self.frame += 1;
+                        } else {
+                            self.frame -= 1;
+                        }
+                    }
+                }
+                PlaybackMode::Once => {
+                    if self.frame == last {
+                        self.finish_cycle();
+                        break;
+                    }
+                    self.frame += 1;
+                }
+            }
+        }
+
+        &self.events
+    }
+
+    /// Counts a finished cycle and stops if it was the last one, returning whether it did.
+    fn finish_cycle(&mut self) -> bool {
+        self.events.push(AnimationEvent::Finished);
+        self.cycles += 1;
+        let done = match self.animation.mode {
+            PlaybackMode::Once => true,
+            PlaybackMode::Loop | PlaybackMode::PingPong => self
+                .animation
+                .repeat
+                .is_some_and(|repeat| self.cycles >= repeat),
+        };
+        if done {
+            self.elapsed = 0.0;
+            self.playing = false;
+            self.finished = true;
+        }
+        done
+    }
+}
+
+impl Engine {
+    /// Draws the current frame of `player`. Nothing is drawn for an animation without frames.
+    pub fn render_animation(
+        &mut self,
+        position: Vec3,
+        scale: Vec3,
+        angle: f32,
+        color: [f32; 4],
+        player: &AnimationPlayer,
+    ) {
+        if let Some(region) = player.current_region() {
+            self.render_region(position, scale, angle, color, region);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextureHandle;
+
+    use AnimationEvent::{Finished, FrameFinished};
+
+    /// `frame_count` frames of one second each.
+    fn new_player(frame_count: usize, mode: PlaybackMode, repeat: Option<u32>) -> AnimationPlayer {
+        let region = TextureRegion::whole(TextureHandle::INVALID);
+        let frames = vec![AnimationFrame::new(region, 1.0); frame_count];
+        let mut animation = Animation::new(frames, mode);
+        animation.set_repeat(repeat);
+        AnimationPlayer::new(animation)
+    }
+
+    /// Frame shown after each of `steps` one second updates.
+    fn frames(player: &mut AnimationPlayer, steps: usize) -> Vec<usize> {
+        (0..steps)
+            .map(|_| {
+                player.update(1.0);
+                player.current_frame()
+            })
+            .collect()
+    }
+
+    fn finished_count(events: &[AnimationEvent]) -> usize {
+        events.iter().filter(|event| **event == Finished).count()
+    }
+
+    #[test]
+    fn loop_wraps_around() {
+        let mut player = new_player(3, PlaybackMode::Loop, None);
+        assert_eq!(player.update(0.5), []);
+        assert_eq!(player.update(0.5), [FrameFinished(0)]);
+        assert_eq!(frames(&mut player, 4), [2, 0, 1, 2]);
+        assert!(player.is_playing());
+    }
+
+    #[test]
+    fn loop_reports_every_cycle() {
+        let mut player = new_player(2, PlaybackMode::Loop, None);
+        assert_eq!(
+            player.update(2.0),
+            [FrameFinished(0), FrameFinished(1), Finished]
+        );
+        assert_eq!(player.current_frame(), 0);
+    }
+
+    #[test]
+    fn large_delta_skips_frames() {
+        let mut player = new_player(3, PlaybackMode::Loop, None);
+        let events = player.update(7.5).to_vec();
+        assert_eq!(events.len(), 7 + 2);
+        assert_eq!(finished_count(&events), 2);
+        assert_eq!(player.current_frame(), 1);
+        // the half second left over carries into the next update
+        assert_eq!(player.update(0.5), [FrameFinished(1)]);
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let mut player = new_player(3, PlaybackMode::PingPong, None);
+        assert_eq!(frames(&mut player, 6), [1, 2, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn ping_pong_reports_each_pass() {
+        let mut player = new_player(3, PlaybackMode::PingPong, None);
+        assert_eq!(finished_count(player.update(3.0)), 1);
+        assert_eq!(player.current_frame(), 1);
+        assert_eq!(finished_count(player.update(1.0)), 0);
+        assert_eq!(player.update(1.0), [FrameFinished(0), Finished]);
+    }
+
+    #[test]
+    fn ping_pong_single_frame() {
+        let mut player = new_player(1, PlaybackMode::PingPong, None);
+        assert_eq!(player.update(1.0), [FrameFinished(0), Finished]);
+        assert_eq!(player.update(2.0).len(), 4);
+        assert_eq!(player.current_frame(), 0);
+        assert!(player.is_playing());
+    }
+
+    #[test]
+    fn once_stops_on_last_frame() {
+        let mut player = new_player(3, PlaybackMode::Once, None);
+        let events = player.update(10.0).to_vec();
+        assert_eq!(
+            events,
+            [
+                FrameFinished(0),
+                FrameFinished(1),
+                FrameFinished(2),
+                Finished
+            ]
+        );
+        assert_eq!(player.current_frame(), 2);
+        assert!(player.is_finished());
+        assert!(!player.is_playing());
+        assert_eq!(player.update(1.0), []);
+    }
+
+    #[test]
+    fn loop_repeat_stops_on_last_frame() {
+        let mut player = new_player(3, PlaybackMode::Loop, Some(2));
+        assert_eq!(finished_count(player.update(5.0)), 1);
+        assert!(!player.is_finished());
+        assert_eq!(finished_count(player.update(100.0)), 1);
+        assert_eq!(player.current_frame(), 2);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_repeat_counts_passes() {
+        // 0 1 2, then 1 0
+        let mut player = new_player(3, PlaybackMode::PingPong, Some(2));
+        assert_eq!(finished_count(player.update(100.0)), 2);
+        assert_eq!(player.current_frame(), 0);
+        assert!(player.is_finished());
+
+        // 0 1 2, 1 0, then 1 2
+        let mut player = new_player(3, PlaybackMode::PingPong, Some(3));
+        assert_eq!(finished_count(player.update(6.5)), 2);
+        assert!(!player.is_finished());
+        assert_eq!(finished_count(player.update(100.0)), 1);
+        assert_eq!(player.current_frame(), 2);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn zero_durations_are_stretched() {
+        let region = TextureRegion::whole(TextureHandle::INVALID);
+        let animation = Animation::new(
+            vec![AnimationFrame::new(region, 0.0); 2],
+            PlaybackMode::Loop,
+        );
+        let mut player = AnimationPlayer::new(animation);
+        let events = player.update(0.01).to_vec();
+        let frames_finished = events.len() - finished_count(&events);
+        // 0.01 s is about ten frames of `MIN_FRAME_DURATION`, rounding may lose one
+        assert!((9..=10).contains(&frames_finished), "{frames_finished}");
+    }
+
+    #[test]
+    fn pause_speed_and_reset() {
+        let mut player = new_player(3, PlaybackMode::Once, None);
+        player.pause();
+        assert_eq!(player.update(5.0), []);
+        player.play();
+        player.set_speed(2.0);
+        assert_eq!(player.update(0.5), [FrameFinished(0)]);
+
+        player.update(10.0);
+        assert!(player.is_finished());
+        player.reset();
+        assert!(!player.is_finished());
+        assert_eq!(player.current_frame(), 0);
+        assert_eq!(player.update(1.0), [FrameFinished(0), FrameFinished(1)]);
+    }
+}
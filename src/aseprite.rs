@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::animation::{Animation, AnimationFrame, PlaybackMode};
+use crate::nine_slice::{BorderInsets, NineSlice};
+use crate::sprite::{RegionRect, TextureRegion};
+use crate::{Engine, EngineError, TextureHandle};
+
+/// Aseprite uses this when a frame has no duration.
+const DEFAULT_FRAME_DURATION_MS: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteFrame {
+    /// Frame name as exported, e.g. `"knight 0.aseprite"`.
+    pub name: String,
+    /// Pixel rect on the sheet texture.
+    pub region: TextureRegion,
+    /// In seconds.
+    pub duration: f32,
+    /// Size of the sprite before transparent borders were trimmed.
+    pub source_size: [u32; 2],
+    /// Where the trimmed rect sits inside `source_size`, `[0, 0]` when not trimmed.
+    pub trim_offset: [u32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsepriteDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+/// A named range of frames, both ends included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: AsepriteDirection,
+    /// How many times the tag plays, `None` for forever.
+    pub repeat: Option<u32>,
+}
+
+/// Slice bounds from one frame onwards. Coordinates are relative to the untrimmed sprite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteSliceKey {
+    pub frame: usize,
+    /// `[x, y, width, height]`
+    pub bounds: [i32; 4],
+    /// Nine-slice center, `[x, y, width, height]` relative to `bounds`.
+    pub center: Option<[i32; 4]>,
+    /// Relative to `bounds`.
+    pub pivot: Option<[i32; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsepriteSlice {
+    pub name: String,
+    pub keys: Vec<AsepriteSliceKey>,
+}
+
+impl AsepriteSlice {
+    /// Key in effect on `frame`, the last one starting at or before it.
+    pub fn key(&self, frame: usize) -> Option<&AsepriteSliceKey> {
+        self.keys.iter().rev().find(|key| key.frame <= frame)
+    }
+}
+
+/// A sprite sheet exported by Aseprite as JSON, either with frames as a hash or an array.
+///
+/// ```ignore
+/// let knight = engine.load_aseprite(String::from("knight"), "assets/knight.json")?;
+/// let mut player = AnimationPlayer::new(knight.animation("walk").unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsepriteSheet {
+    /// Path of the sheet image as written in the JSON, relative to the JSON file.
+    pub image: PathBuf,
+    frames: Vec<AsepriteFrame>,
+    tags: Vec<AsepriteTag>,
+    slices: HashMap<String, AsepriteSlice>,
+}
+
+impl AsepriteSheet {
+    /// Parses the JSON export. Frame regions point at `texture`, the sheet image isn't loaded.
+    pub fn from_json_str(texture: TextureHandle, source: &str) -> Result<Self, serde_json::Error> {
+        Self::from_raw(texture, serde_json::from_str(source)?)
+    }
+
+    fn from_raw(texture: TextureHandle, raw: RawSheet) -> Result<Self, serde_json::Error> {
+        let raw_frames = match raw.frames {
+            RawFrames::Array(frames) => frames,
+            RawFrames::Hash(frames) => frames
+                .into_iter()
+                .map(|(name, frame)| {
+                    let mut frame: RawFrame = serde_json::from_value(frame)?;
+                    frame.filename = name;
+                    Ok(frame)
+                })
+                .collect::<Result<_, serde_json::Error>>()?,
+        };
+
+        let frames = raw_frames
+            .into_iter()
+            .map(|frame| {
+                if frame.rotated {
+                    log::warn!(
+                        "Frame '{}' is rotated on the sheet, which isn't supported",
+                        frame.filename
+                    );
+                }
+                AsepriteFrame {
+                    name: frame.filename,
+                    region: TextureRegion::from_pixels(
+                        texture,
+                        frame.frame.x,
+                        frame.frame.y,
+                        frame.frame.w,
+                        frame.frame.h,
+                    ),
+                    duration: frame.duration.unwrap_or(DEFAULT_FRAME_DURATION_MS) as f32 / 1000.0,
+                    source_size: frame
+                        .source_size
+                        .map_or([frame.frame.w, frame.frame.h], |size| [size.w, size.h]),
+                    trim_offset: frame
+                        .sprite_source_size
+                        .filter(|_| frame.trimmed)
+                        .map_or([0, 0], |rect| [rect.x, rect.y]),
+                }
+            })
+            .collect();
+
+        let tags = raw
+            .meta
+            .frame_tags
+            .into_iter()
+            .map(|tag| AsepriteTag {
+                direction: match tag.direction.as_str() {
+                    "reverse" => AsepriteDirection::Reverse,
+                    "pingpong" => AsepriteDirection::PingPong,
+                    "pingpong_reverse" => AsepriteDirection::PingPongReverse,
+                    _ => AsepriteDirection::Forward,
+                },
+                // Aseprite writes 0 or nothing for forever
+                repeat: tag
+                    .repeat
+                    .and_then(|repeat| repeat.parse().ok())
+                    .filter(|&repeat| repeat > 0),
+                name: tag.name,
+                from: tag.from,
+                to: tag.to,
+            })
+            .collect();
+
+        let slices = raw
+            .meta
+            .slices
+            .into_iter()
+            .map(|slice| {
+                let keys = slice
+                    .keys
+                    .into_iter()
+                    .map(|key| AsepriteSliceKey {
+                        frame: key.frame,
+                        bounds: key.bounds.to_array(),
+                        center: key.center.map(|center| center.to_array()),
+                        pivot: key.pivot.map(|pivot| [pivot.x, pivot.y]),
+                    })
+                    .collect();
+                (
+                    slice.name.clone(),
+                    AsepriteSlice {
+                        name: slice.name,
+                        keys,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            image: PathBuf::from(raw.meta.image),
+            frames,
+            tags,
+            slices,
+        })
+    }
+
+    pub fn frames(&self) -> &[AsepriteFrame] {
+        &self.frames
+    }
+
+    pub fn tags(&self) -> &[AsepriteTag] {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&AsepriteTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    pub fn slice(&self, name: &str) -> Option<&AsepriteSlice> {
+        self.slices.get(name)
+    }
+
+    pub fn slices(&self) -> impl Iterator<Item = &AsepriteSlice> {
+        self.slices.values()
+    }
+
+    /// Every frame in order, as one looping animation.
+    pub fn full_animation(&self) -> Animation {
+        let frames = self.frames.iter().map(Self::animation_frame).collect();
+        Animation::new(frames, PlaybackMode::Loop)
+    }
+
+    /// The frames of the tag called `name`. Tags with a repeat count stop after that many passes,
+    /// which for ping-pong tags counts each direction like Aseprite does.
+    pub fn animation(&self, name: &str) -> Option<Animation> {
+        let tag = self.tag(name)?;
+        let mut frames: Vec<AnimationFrame> = self
+            .frames
+            .get(tag.from..=tag.to.min(self.frames.len().saturating_sub(1)))?
+            .iter()
+            .map(Self::animation_frame)
+            .collect();
+
+        if matches!(
+            tag.direction,
+            AsepriteDirection::Reverse | AsepriteDirection::PingPongReverse
+        ) {
+            frames.reverse();
+        }
+
+        let mode = match tag.direction {
+            AsepriteDirection::PingPong | AsepriteDirection::PingPongReverse => {
+                PlaybackMode::PingPong
+            }
+            AsepriteDirection::Forward | AsepriteDirection::Reverse => PlaybackMode::Loop,
+        };
+        let mut animation = Animation::new(frames, mode);
+        animation.set_repeat(tag.repeat);
+        Some(animation)
+    }
+
+    /// Pixel rect on the sheet texture covered by slice `name` on `frame`.
+    pub fn slice_region(&self, name: &str, frame: usize) -> Option<TextureRegion> {
+        let key = self.slice(name)?.key(frame)?;
+        let sheet_frame = self.frames.get(frame)?;
+        let (x, y) = match sheet_frame.region.rect {
+            RegionRect::Pixels { x, y, .. } => (x as i32, y as i32),
+            RegionRect::Normalized(_) => return None,
+        };
+
+        let [bounds_x, bounds_y, width, height] = key.bounds;
+        let x = x + bounds_x - sheet_frame.trim_offset[0] as i32;
+        let y = y + bounds_y - sheet_frame.trim_offset[1] as i32;
+        Some(TextureRegion::from_pixels(
+            sheet_frame.region.texture,
+            x.max(0) as u32,
+            y.max(0) as u32,
+            width.max(0) as u32,
+            height.max(0) as u32,
+        ))
+    }
+
+    /// Slice `name` on `frame` as a nine-slice, `None` if the slice has no 9-slices center.
+    pub fn nine_slice(&self, name: &str, frame: usize) -> Option<NineSlice> {
+        let key = self.slice(name)?.key(frame)?;
+        let [x, y, width, height] = key.center?;
+        let [_, _, bounds_width, bounds_height] = key.bounds;
+        let insets = BorderInsets::new(
+            x.max(0) as u32,
+            (bounds_width - x - width).max(0) as u32,
+            y.max(0) as u32,
+            (bounds_height - y - height).max(0) as u32,
+        );
+        Some(NineSlice::new(self.slice_region(name, frame)?, insets))
+    }
+
+    fn animation_frame(frame: &AsepriteFrame) -> AnimationFrame {
+        AnimationFrame::new(frame.region, frame.duration)
+    }
+}
+
+impl Engine {
+    /// Loads an Aseprite JSON export and creates its image as the texture `id`,
+    /// the same way `create_texture` does.
+    pub fn load_aseprite(
+        &mut self,
+        id: String,
+        json_path: impl AsRef<Path>,
+    ) -> Result<AsepriteSheet, EngineError> {
+        let json_path = json_path.as_ref();
+        let source = std::fs::read_to_string(json_path).map_err(|source| EngineError::Io {
+            path: json_path.to_path_buf(),
+            source,
+        })?;
+        let invalid_sheet = |source| EngineError::Aseprite {
+            path: json_path.to_path_buf(),
+            source,
+        };
+        let raw: RawSheet = serde_json::from_str(&source).map_err(invalid_sheet)?;
+
+        let image_path = json_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&raw.meta.image);
+        let texture = self.create_texture(id, image_path)?;
+        AsepriteSheet::from_raw(texture, raw).map_err(invalid_sheet)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawSheet {
+    frames: RawFrames,
+    meta: RawMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawFrames {
+    Array(Vec<RawFrame>),
+    // kept in file order thanks to serde_json's `preserve_order`
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFrame {
+    #[serde(default)]
+    filename: String,
+    frame: RawRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Option<RawRect>,
+    source_size: Option<RawSize>,
+    duration: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMeta {
+    image: String,
+    #[serde(default)]
+    frame_tags: Vec<RawTag>,
+    #[serde(default)]
+    slices: Vec<RawSlice>,
+}
+
+#[derive(Deserialize)]
+struct RawTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    repeat: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawSlice {
+    name: String,
+    keys: Vec<RawSliceKey>,
+}
+
+#[derive(Deserialize)]
+struct RawSliceKey {
+    frame: usize,
+    bounds: RawSignedRect,
+    center: Option<RawSignedRect>,
+    pivot: Option<RawPoint>,
+}
+
+#[derive(Deserialize)]
+struct RawRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct RawSignedRect {
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+}
+
+impl RawSignedRect {
+    fn to_array(&self) -> [i32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+}
+
+#[derive(Deserialize)]
+struct RawSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct RawPoint {
+    x: i32,
+    y: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AnimationPlayer;
+
+    const TAGS: &str = r#"[
+        { "name": "walk", "from": 0, "to": 2, "direction": "forward" },
+        { "name": "back", "from": 0, "to": 2, "direction": "reverse", "repeat": "0" },
+        { "name": "bounce", "from": 0, "to": 2, "direction": "pingpong", "repeat": "3" },
+        { "name": "sway", "from": 1, "to": 2, "direction": "pingpong_reverse", "repeat": "2" },
+        { "name": "hit", "from": 2, "to": 2, "direction": "forward", "repeat": "1" },
+        { "name": "past", "from": 5, "to": 6, "direction": "forward" }
+    ]"#;
+
+    fn frame(x: u32) -> String {
+        format!(r#"{{ "frame": {{ "x": {x}, "y": 0, "w": 16, "h": 16 }}, "duration": 100 }}"#)
+    }
+
+    fn hash_sheet() -> AsepriteSheet {
+        let json = format!(
+            r#"{{
+                "frames": {{ "a 0": {}, "a 1": {}, "a 2": {} }},
+                "meta": {{ "image": "a.png", "frameTags": {TAGS} }}
+            }}"#,
+            frame(0),
+            frame(16),
+            frame(32)
+        );
+        AsepriteSheet::from_json_str(TextureHandle::INVALID, &json).unwrap()
+    }
+
+    fn array_sheet() -> AsepriteSheet {
+        let json = format!(
+            r#"{{
+                "frames": [{}, {}, {}],
+                "meta": {{ "image": "a.png", "frameTags": {TAGS} }}
+            }}"#,
+            frame(0),
+            frame(16),
+            frame(32)
+        );
+        AsepriteSheet::from_json_str(TextureHandle::INVALID, &json).unwrap()
+    }
+
+    /// Sheet x of each frame, frames are 16 pixels apart.
+    fn frame_xs(animation: &Animation) -> Vec<u32> {
+        animation
+            .frames()
+            .iter()
+            .map(|frame| match frame.region.rect {
+                RegionRect::Pixels { x, .. } => x,
+                RegionRect::Normalized(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    /// Plays `animation` to its end and returns the frame it stopped on.
+    fn last_frame(animation: Animation) -> Option<usize> {
+        let mut player = AnimationPlayer::new(animation);
+        player.update(100.0);
+        player.is_finished().then(|| player.current_frame())
+    }
+
+    #[test]
+    fn hash_and_array_frames() {
+        for sheet in [hash_sheet(), array_sheet()] {
+            assert_eq!(frame_xs(&sheet.full_animation()), [0, 16, 32]);
+            assert_eq!(sheet.frames()[2].duration, 0.1);
+        }
+        let names: Vec<_> = hash_sheet()
+            .frames()
+            .iter()
+            .map(|f| f.name.clone())
+            .collect();
+        assert_eq!(names, ["a 0", "a 1", "a 2"]);
+    }
+
+    #[test]
+    fn loop_tags() {
+        for sheet in [hash_sheet(), array_sheet()] {
+            let walk = sheet.animation("walk").unwrap();
+            assert_eq!(walk.mode(), PlaybackMode::Loop);
+            assert_eq!(walk.repeat(), None);
+            assert_eq!(frame_xs(&walk), [0, 16, 32]);
+
+            let back = sheet.animation("back").unwrap();
+            assert_eq!(back.mode(), PlaybackMode::Loop);
+            assert_eq!(back.repeat(), None);
+            assert_eq!(frame_xs(&back), [32, 16, 0]);
+        }
+    }
+
+    #[test]
+    fn ping_pong_tags() {
+        for sheet in [hash_sheet(), array_sheet()] {
+            let bounce = sheet.animation("bounce").unwrap();
+            assert_eq!(bounce.mode(), PlaybackMode::PingPong);
+            assert_eq!(bounce.repeat(), Some(3));
+            // there, back and there again
+            assert_eq!(last_frame(bounce), Some(2));
+
+            let sway = sheet.animation("sway").unwrap();
+            assert_eq!(sway.mode(), PlaybackMode::PingPong);
+            assert_eq!(frame_xs(&sway), [32, 16]);
+            // there and back, ending where the reversed tag started
+            assert_eq!(last_frame(sway), Some(0));
+        }
+    }
+
+    #[test]
+    fn tags_played_once() {
+        for sheet in [hash_sheet(), array_sheet()] {
+            let hit = sheet.animation("hit").unwrap();
+            assert_eq!(hit.repeat(), Some(1));
+            assert_eq!(frame_xs(&hit), [32]);
+            assert_eq!(last_frame(hit), Some(0));
+
+            assert_eq!(last_frame(sheet.animation("walk").unwrap()), None);
+        }
+    }
+
+    #[test]
+    fn missing_tags() {
+        let sheet = hash_sheet();
+        assert!(sheet.animation("run").is_none());
+        assert!(sheet.animation("past").is_none());
+    }
+}
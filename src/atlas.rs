@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use image::{DynamicImage, GenericImage, RgbaImage};
+
+use crate::sprite::TextureRegion;
+use crate::texture::load_image;
+use crate::{Engine, EngineError, TextureHandle, TextureOptions};
+
+/// Images packed into one or a few large textures ("pages") by `TextureAtlasBuilder`.
+pub struct TextureAtlas {
+    pages: Vec<TextureHandle>,
+    /// Pixel rects on the pages.
+    regions: HashMap<String, TextureRegion>,
+}
+
+impl TextureAtlas {
+    pub fn region(&self, name: &str) -> Option<&TextureRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = (&str, &TextureRegion)> {
+        self.regions
+            .iter()
+            .map(|(name, region)| (name.as_str(), region))
+    }
+
+    /// Textures of the pages, registered in the engine as `"{id}#0"`, `"{id}#1"`, ...
+    pub fn pages(&self) -> &[TextureHandle] {
+        &self.pages
+    }
+}
+
+/// Packs images into atlas pages.
+///
+/// ```ignore
+/// let mut builder = TextureAtlasBuilder::new().page_size(1024);
+/// builder.add_file("tree", "src/happy-tree.png")?;
+/// builder.add_file("pumpkin", "src/pumpkin.png")?;
+/// let atlas = builder.build(engine, "level-1")?;
+///
+/// engine.render_region(position, scale, 0.0, color, atlas.region("tree").unwrap());
+/// ```
+pub struct TextureAtlasBuilder {
+    page_size: u32,
+    padding: u32,
+    options: TextureOptions,
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl Default for TextureAtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureAtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            page_size: 2048,
+            padding: 1,
+            options: TextureOptions::default(),
+            images: vec![],
+        }
+    }
+
+    /// Width and height of every page. Clamped to what the device supports when building.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Pixels around every image, filled with copies of its edges so filtering and mipmaps
+    /// don't bleed neighbours or transparency into it.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// How the pages are created, e.g. `SamplerOptions::pixel_art()`.
+    pub fn options(mut self, options: impl Into<TextureOptions>) -> Self {
+        self.options = options.into();
+        self
+    }
+
+    pub fn add_image(&mut self, name: impl Into<String>, image: &DynamicImage) {
+        self.images.push((name.into(), image.to_rgba8()));
+    }
+
+    pub fn add_file(
+        &mut self,
+        name: impl Into<String>,
+        path: impl AsRef<Path>,
+    ) -> Result<(), EngineError> {
+        let image = load_image(path.as_ref())?;
+        self.add_image(name, &image);
+        Ok(())
+    }
+
+    /// Packs every image and registers the pages in the engine as `"{id}#0"`, `"{id}#1"`, ...
+    pub fn build(self, engine: &mut Engine, id: &str) -> Result<TextureAtlas, EngineError> {
+        let page_size = self.page_size.min(engine.max_texture_dimension());
+
+        let mut images = self.images;
+        // Tallest first keeps the shelves tight.
+        images.sort_by_key(|(_, image)| std::cmp::Reverse(image.height()));
+
+        let mut packers: Vec<ShelfPacker> = vec![];
+        let mut page_images: Vec<RgbaImage> = vec![];
+        // (name, page, x, y, width, height), turned into regions once the pages have handles
+        let mut placements = vec![];
+
+        for (name, image) in images {
+            let (width, height) = image.dimensions();
+            if width + 2 * self.padding > page_size || height + 2 * self.padding > page_size {
+                return Err(EngineError::AtlasImageTooLarge {
+                    name,
+                    width,
+                    height,
+                    page_size,
+                });
+            }
+
+            let packed = packers
+                .iter_mut()
+                .enumerate()
+                .find_map(|(page, packer)| packer.pack(width, height).map(|pos| (page, pos)));
+
+            let (page, (x, y)) = match packed {
+                Some(packed) => packed,
+                None => {
+                    let mut packer = ShelfPacker::new(page_size, page_size, self.padding);
+                    let pos = packer.pack(width, height).unwrap();
+                    packers.push(packer);
+                    page_images.push(RgbaImage::new(page_size, page_size));
+                    (packers.len() - 1, pos)
+                }
+            };
+
+            page_images[page].copy_from(&image, x, y).unwrap();
+            extrude(&mut page_images[page], &image, x, y, self.padding);
+
+            placements.push((name, page, x, y, width, height));
+        }
+
+        let pages: Vec<TextureHandle> = page_images
+            .into_iter()
+            .enumerate()
+            .map(|(page, page_image)| {
+                engine.create_texture_from_image_with_options(
+                    format!("{}#{}", id, page),
+                    &DynamicImage::ImageRgba8(page_image),
+                    self.options,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+
+        let regions = placements
+            .into_iter()
+            .map(|(name, page, x, y, width, height)| {
+                let region = TextureRegion::from_pixels(pages[page], x, y, width, height);
+                (name, region)
+            })
+            .collect();
+
+        Ok(TextureAtlas { pages, regions })
+    }
+}
+
+/// Repeats the border pixels of `image`, placed at `x`, `y` on `page`, into the `padding`
+/// around it.
+fn extrude(page: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, padding: u32) {
+    let (width, height) = image.dimensions();
+    if padding == 0 || width == 0 || height == 0 {
+        return;
+    }
+    // the packer keeps the padding inside the page
+    for page_y in y - padding..y + height + padding {
+        let source_y = page_y.clamp(y, y + height - 1) - y;
+        for page_x in x - padding..x + width + padding {
+            let inside = (x..x + width).contains(&page_x) && (y..y + height).contains(&page_y);
+            if !inside {
+                let source_x = page_x.clamp(x, x + width - 1) - x;
+                page.put_pixel(page_x, page_y, *image.get_pixel(source_x, source_y));
+            }
+        }
+    }
+}
+
+/// Rectangle packer that fills the page in horizontal shelves, each as tall as the first
+/// rectangle placed in it. Simple and good enough for sprites of similar heights.
+pub(crate) struct ShelfPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    // where the next rectangle goes
+    cursor_x: u32,
+}
+
+impl ShelfPacker {
+    pub(crate) fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            shelves: vec![],
+        }
+    }
+
+    /// Returns the top-left corner of the `width` x `height` rectangle, padding excluded,
+    /// or `None` if the page is full.
+    pub(crate) fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let padded_width = width + 2 * self.padding;
+        let padded_height = height + 2 * self.padding;
+
+        // Best fit: the shortest shelf the rectangle fits in.
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                shelf.height >= padded_height && shelf.cursor_x + padded_width <= self.width
+            })
+            .min_by_key(|shelf| shelf.height);
+
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height);
+                if y + padded_height > self.height || padded_width > self.width {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    cursor_x: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let position = (shelf.cursor_x + self.padding, shelf.y + self.padding);
+        shelf.cursor_x += padded_width;
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding_surrounds_every_rect() {
+        let mut packer = ShelfPacker::new(100, 100, 2);
+        assert_eq!(packer.pack(10, 10), Some((2, 2)));
+        assert_eq!(packer.pack(10, 10), Some((16, 2)));
+    }
+
+    #[test]
+    fn full_shelf_starts_a_new_one() {
+        let mut packer = ShelfPacker::new(30, 100, 1);
+        assert_eq!(packer.pack(10, 10), Some((1, 1)));
+        assert_eq!(packer.pack(10, 10), Some((13, 1)));
+        assert_eq!(packer.pack(10, 10), Some((1, 13)));
+    }
+
+    #[test]
+    fn shortest_fitting_shelf_is_used() {
+        let mut packer = ShelfPacker::new(100, 100, 1);
+        assert_eq!(packer.pack(10, 20), Some((1, 1)));
+        assert_eq!(packer.pack(90, 10), Some((1, 23)));
+        // both shelves have room, the second one is shorter
+        assert_eq!(packer.pack(5, 5), Some((93, 23)));
+        // only the first one is tall enough
+        assert_eq!(packer.pack(5, 15), Some((13, 1)));
+    }
+
+    #[test]
+    fn too_large_rects_are_rejected() {
+        let mut packer = ShelfPacker::new(32, 32, 1);
+        assert_eq!(packer.pack(31, 10), None);
+        assert_eq!(packer.pack(10, 31), None);
+        // padding included, it just fits
+        assert_eq!(packer.pack(30, 30), Some((1, 1)));
+    }
+
+    #[test]
+    fn full_page_returns_none() {
+        let mut packer = ShelfPacker::new(24, 24, 1);
+        assert_eq!(packer.pack(10, 10), Some((1, 1)));
+        assert_eq!(packer.pack(10, 10), Some((13, 1)));
+        assert_eq!(packer.pack(10, 10), Some((1, 13)));
+        assert_eq!(packer.pack(10, 10), Some((13, 13)));
+        assert_eq!(packer.pack(10, 10), None);
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn no_padding() {
+        let mut packer = ShelfPacker::new(20, 20, 0);
+        assert_eq!(packer.pack(20, 20), Some((0, 0)));
+        assert_eq!(packer.pack(1, 1), None);
+    }
+
+    #[test]
+    fn edges_are_extruded_into_padding() {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([0, 0, 255, 255]));
+        let mut page = RgbaImage::new(4, 3);
+        page.copy_from(&image, 1, 1).unwrap();
+        extrude(&mut page, &image, 1, 1, 1);
+
+        let red = image::Rgba([255, 0, 0, 255]);
+        let blue = image::Rgba([0, 0, 255, 255]);
+        for y in 0..3 {
+            let row: Vec<_> = (0..4).map(|x| *page.get_pixel(x, y)).collect();
+            assert_eq!(row, [red, red, blue, blue], "row {y}");
+        }
+    }
+}
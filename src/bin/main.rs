@@ -1,7 +1,11 @@
 #![allow(unused, dead_code)]
+use std::collections::HashMap;
 use std::f32::consts::{FRAC_2_PI, FRAC_PI_2, FRAC_PI_4, FRAC_PI_6, FRAC_PI_8};
 
-use bm::async_runner;
+use bm::{
+    async_runner_with_config, Animation, AnimationEvent, AnimationFrame, AnimationPlayer,
+    PlaybackMode, TextureHandle, TextureRegion,
+};
 use glam::*;
 use winit::{
     event::{ElementState, Event, WindowEvent},
@@ -19,11 +23,12 @@ pub struct Enemy<'a> {
     scale_y: f32,
     color: [f32; 4],
     health: f32,
-    texture_id: String,
+    texture: Option<TextureHandle>,
+    animation: Option<AnimationPlayer>,
 }
 
 impl<'a> Enemy<'a> {
-    pub fn new(x: f32, y: f32, color: [f32; 4], label: Label<'a>, texture_id: String) -> Self {
+    pub fn new(x: f32, y: f32, color: [f32; 4], label: Label<'a>, texture: TextureHandle) -> Self {
         Self {
             x,
             y,
@@ -32,11 +37,25 @@ impl<'a> Enemy<'a> {
             color,
             label,
             health: 100.0,
-            texture_id,
+            texture: Some(texture),
+            animation: None,
         }
     }
 
+    /// Shown instead of the static texture.
+    pub fn with_animation(mut self, animation: Animation) -> Self {
+        self.animation = Some(AnimationPlayer::new(animation));
+        self
+    }
+
     pub fn on_update(&mut self, engine: &mut bm::Engine, new_pos: (f32, f32), delta_time: f32) {
+        if let Some(animation) = &mut self.animation {
+            for event in animation.update(delta_time) {
+                if let AnimationEvent::Finished = event {
+                    log::debug!("{:?} finished a cycle", self.label);
+                }
+            }
+        }
         if let Some(val) = self.label {
             if val == "Enemy 1" {
                 // println!("Enemy 1: {:?}, {:?}", (self.x, self.y), self.health);
@@ -54,14 +73,13 @@ impl<'a> Enemy<'a> {
         let scale = Vec3::new(self.scale_x, self.scale_y, 1.0);
         let angle: f32 = 0.0;
 
+        if let Some(animation) = &self.animation {
+            engine.render_animation(position, scale, angle, self.color, animation);
+            return;
+        }
+
         // engine.render_quad(position, scale, angle, self.color, Some(include_bytes!("pikachu.png")));
-        engine.render_quad(
-            position,
-            scale,
-            angle,
-            self.color,
-            Some(self.texture_id.clone()),
-        );
+        engine.render_quad(position, scale, angle, self.color, self.texture);
     }
 }
 
@@ -149,6 +167,7 @@ impl Player {
                 state: ElementState::Released,
                 ..
             } => 0.0,
+            _ => return,
         };
 
         match event {
@@ -294,13 +313,11 @@ impl Player {
 struct App<'a> {
     player: Player,
     container: EnemyContainer<'a>,
+    textures_loading: bool,
 }
 
 impl<'a> App<'a> {
     pub fn new() -> Self {
-        let mut container = EnemyContainer::default();
-        App::create_enemies(&mut container);
-
         let player = Player {
             x: 500.0,
             y: 300.0,
@@ -314,17 +331,30 @@ impl<'a> App<'a> {
             scale_y: 130.0,
             speed: 5.0,
         };
-        Self { container, player }
+        Self {
+            container: EnemyContainer::default(),
+            player,
+            textures_loading: false,
+        }
     }
 
-    fn create_enemies(container: &mut EnemyContainer) {
+    fn create_enemies(container: &mut EnemyContainer, textures: &HashMap<&str, TextureHandle>) {
         let color1: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
         let color2: [f32; 4] = [1.0, 0.0, 0.0, 0.3];
         let circle_color: [f32; 4] = [1.0, 0.0, 0.0, 0.3];
 
-        let enemy1 = Enemy::new(200.0, 300.0, color1, Some("Cube 1"), String::from("tree"));
-        let enemy2 = Enemy::new(300.0, 200.0, color2, Some("Cube 2"), String::from("tree"));
-        let enemy_pika = Enemy::new(500.0, 500.0, color1, Some("Cube 2"), String::from("pika"));
+        let enemy1 = Enemy::new(200.0, 300.0, color1, Some("Cube 1"), textures["tree"]);
+        let enemy2 = Enemy::new(300.0, 200.0, color2, Some("Cube 2"), textures["tree"]);
+        let shapeshifter = Animation::new(
+            vec![
+                AnimationFrame::new(TextureRegion::whole(textures["pika"]), 0.6),
+                AnimationFrame::new(TextureRegion::whole(textures["pumpkin"]), 0.3),
+                AnimationFrame::new(TextureRegion::whole(textures["tree"]), 0.3),
+            ],
+            PlaybackMode::PingPong,
+        );
+        let enemy_pika = Enemy::new(500.0, 500.0, color1, Some("Cube 2"), textures["pika"])
+            .with_animation(shapeshifter);
         // let circle_enemy = CircleEnemy::new(100.0, 100.0, circle_color);
 
         container.add_enemy(enemy1);
@@ -336,14 +366,38 @@ impl<'a> App<'a> {
 
 impl<'a> bm::Application for App<'a> {
     fn on_setup(&mut self, engine: &mut bm::Engine) {
-        engine.create_texture(String::from("tree"), "src/happy-tree.png");
-        engine.create_texture(String::from("pika"), "src/default.png");
-        engine.create_texture(String::from("sims"), "src/sims.png");
-        engine.create_texture(String::from("dvd"), "src/power-dvd.jpg");
-        engine.create_texture(String::from("pumpkin"), "src/pumpkin.png");
+        let textures = [
+            ("tree", "src/happy-tree.png"),
+            ("pika", "src/default.png"),
+            ("sims", "src/sims.png"),
+            ("dvd", "src/power-dvd.jpg"),
+            ("pumpkin", "src/pumpkin.png"),
+        ];
+
+        // Decoded in the background, the enemies show the placeholder until then.
+        let mut handles = HashMap::new();
+        for (id, path) in textures {
+            let handle = engine.load_texture_async(String::from(id), path);
+            handles.insert(id, handle);
+        }
+        self.textures_loading = true;
+
+        App::create_enemies(&mut self.container, &handles);
     }
 
     fn on_update(&mut self, engine: &mut bm::Engine, delta_time: f32) {
+        if self.textures_loading {
+            let progress = engine.texture_load_progress();
+            if progress.is_done() {
+                self.textures_loading = false;
+                log::info!(
+                    "Loaded {} textures ({} failed), {:?}",
+                    progress.total,
+                    progress.failed,
+                    engine.texture_stats()
+                );
+            }
+        }
         self.player.update(engine, delta_time);
         self.container.on_update(engine, &self.player, delta_time);
     }
@@ -360,5 +414,14 @@ impl<'a> bm::Application for App<'a> {
 
 pub fn main() {
     let mut app = App::new();
-    pollster::block_on(async_runner(app));
+    let engine_config = bm::EngineConfig {
+        quit_key: Some(KeyCode::KeyQ),
+        // pick up edits to src/*.png while the game runs
+        texture_hot_reload: cfg!(debug_assertions),
+        ..Default::default()
+    };
+    if let Err(err) = pollster::block_on(async_runner_with_config(app, engine_config)) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
 }
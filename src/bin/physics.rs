@@ -1,16 +1,24 @@
-use bm::async_runner;
+use bm::async_runner_with_config;
 
 struct App;
 impl bm::Application for App {
-    fn on_setup(&mut self, engine: &mut bm::Engine) {}
+    fn on_setup(&mut self, _engine: &mut bm::Engine) {}
 
-    fn on_update(&mut self, engine: &mut bm::Engine, delta_time: f32) {}
+    fn on_update(&mut self, _engine: &mut bm::Engine, _delta_time: f32) {}
 
-    fn on_render(&mut self, engine: &mut bm::Engine) {}
+    fn on_render(&mut self, _engine: &mut bm::Engine) {}
 
-    fn on_event(&mut self, engine: &mut bm::Engine, event: bm::MyEvent) {}
+    fn on_event(&mut self, _engine: &mut bm::Engine, _event: bm::MyEvent) {}
 }
 pub fn main() {
     let app = App;
-    pollster::block_on(async_runner(app));
+    let engine_config = bm::EngineConfig {
+        title: String::from("Physics"),
+        window_mode: bm::WindowMode::Windowed,
+        ..Default::default()
+    };
+    if let Err(err) = pollster::block_on(async_runner_with_config(app, engine_config)) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,127 @@
+use crate::context::BINDLESS_TEXTURE_FEATURES;
+use crate::texture::TextureStore;
+use crate::TextureHandle;
+
+/// More textures per array barely saves draw calls but makes every bind group bigger.
+const MAX_TEXTURES: u32 = 256;
+
+/// Binding arrays of textures and samplers for the quad pipeline. Quads select their texture by
+/// index, so a draw call only ends once `capacity` different textures have been used.
+pub(crate) struct BindlessTextures {
+    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    // textures per bind group
+    pub(crate) capacity: usize,
+    // this frame's bind groups with the textures they hold, reused while the textures don't change
+    bind_groups: Vec<(Vec<TextureHandle>, wgpu::BindGroup)>,
+    textures_version: u64,
+}
+
+impl BindlessTextures {
+    /// `None` when the device can't index textures per instance.
+    pub(crate) fn new(device: &wgpu::Device) -> Option<Self> {
+        if !device.features().contains(BINDLESS_TEXTURE_FEATURES) {
+            return None;
+        }
+
+        let limits = device.limits();
+        let capacity = limits
+            .max_sampled_textures_per_shader_stage
+            .min(limits.max_samplers_per_shader_stage)
+            .min(MAX_TEXTURES);
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bindless_texture_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: std::num::NonZeroU32::new(capacity),
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: std::num::NonZeroU32::new(capacity),
+                },
+            ],
+        });
+
+        Some(Self {
+            bind_group_layout,
+            capacity: capacity as usize,
+            bind_groups: vec![],
+            textures_version: 0,
+        })
+    }
+
+    pub(crate) fn bind_group(&self, index: usize) -> &wgpu::BindGroup {
+        &self.bind_groups[index].1
+    }
+
+    /// Makes one bind group per entry of `texture_sets`, in order. Every handle has to point at
+    /// a texture, the unused slots are filled with `filler`.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        textures: &TextureStore,
+        texture_sets: Vec<Vec<TextureHandle>>,
+        filler: TextureHandle,
+    ) {
+        let mut previous = std::mem::take(&mut self.bind_groups);
+        // a texture was replaced in place, the old bind groups would still draw it
+        if self.textures_version != textures.version() {
+            previous.clear();
+            self.textures_version = textures.version();
+        }
+
+        for texture_set in texture_sets {
+            let bind_group = match previous.iter().position(|(set, _)| *set == texture_set) {
+                Some(index) => previous.swap_remove(index).1,
+                None => self.create_bind_group(device, textures, &texture_set, filler),
+            };
+            self.bind_groups.push((texture_set, bind_group));
+        }
+    }
+
+    fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        textures: &TextureStore,
+        texture_set: &[TextureHandle],
+        filler: TextureHandle,
+    ) -> wgpu::BindGroup {
+        let textures: Vec<_> = texture_set
+            .iter()
+            .chain(std::iter::repeat(&filler))
+            .take(self.capacity)
+            .map(|&handle| &textures.get(handle).unwrap().texture)
+            .collect();
+        let views: Vec<_> = textures
+            .iter()
+            .map(|texture| &texture.texture_view)
+            .collect();
+        let samplers: Vec<_> = textures
+            .iter()
+            .map(|texture| &texture.texture_sampler)
+            .collect();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("bindless texture bindgroup"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureViewArray(&views),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::SamplerArray(&samplers),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use glam::Vec2;
+use serde::Deserialize;
+
+use crate::font::{Font, Glyph, LineMetrics};
+use crate::{Engine, EngineError, SpriteSheet, SpriteSheetGrid, TextureRegion};
+
+/// A font drawn from glyphs on a texture, either a monospace grid or a table of glyphs of
+/// any width. Scaled from the size it was made for, so it looks best at multiples of it.
+///
+/// Made in code, with `Engine::load_bitmap_font` or from BMFont files with `Engine::load_bmfont`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitmapFont {
+    // size in pixels the metrics below are for
+    size: f32,
+    line_height: f32,
+    // from the top of a line to the baseline
+    base: f32,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+    // drawn for characters the font doesn't have
+    fallback: Option<char>,
+}
+
+impl BitmapFont {
+    /// An empty font to add glyphs to. `line_height` and `base` (from the top of a line to the
+    /// baseline) are in pixels at font size `size`.
+    pub fn new(size: f32, line_height: f32, base: f32) -> Self {
+        Self {
+            size,
+            line_height,
+            base,
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// One glyph per frame of `sheet`, `chars` in frame order. Every glyph is a whole cell with
+    /// the baseline at its bottom.
+    pub fn monospace(sheet: &SpriteSheet, chars: &str) -> Self {
+        let (cell_width, cell_height) = sheet
+            .frame(0)
+            .and_then(TextureRegion::pixel_size)
+            .unwrap_or_default();
+        let (cell_width, cell_height) = (cell_width as f32, cell_height as f32);
+
+        let mut font = Self::new(cell_height, cell_height, cell_height);
+        for (ch, region) in chars.chars().zip(sheet.frames()) {
+            font.add_glyph(
+                ch,
+                Some(*region),
+                Vec2::ZERO,
+                Vec2::new(cell_width, cell_height),
+                cell_width,
+            );
+        }
+        font
+    }
+
+    /// `offset` goes from the pen position to the glyph's top-left corner, y going down from
+    /// the top of the line (BMFont's `xoffset` and `yoffset`).
+    pub fn add_glyph(
+        &mut self,
+        ch: char,
+        region: Option<TextureRegion>,
+        offset: Vec2,
+        size: Vec2,
+        advance: f32,
+    ) {
+        let glyph = Glyph {
+            region,
+            offset: Vec2::new(offset.x, offset.y - self.base),
+            size,
+            advance,
+        };
+        self.glyphs.insert(ch, glyph);
+    }
+
+    pub fn add_kerning(&mut self, left: char, right: char, amount: f32) {
+        self.kerning.insert((left, right), amount);
+    }
+
+    /// Drawn instead of characters the font doesn't have, e.g. `'?'`.
+    pub fn set_fallback(&mut self, fallback: Option<char>) {
+        self.fallback = fallback;
+    }
+
+    pub fn with_fallback(mut self, fallback: char) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn has_glyph(&self, ch: char) -> bool {
+        self.glyphs.contains_key(&ch)
+    }
+}
+
+impl Font for BitmapFont {
+    fn line_metrics(&self, size: f32) -> LineMetrics {
+        let scale = size / self.size;
+        LineMetrics {
+            ascent: self.base * scale,
+            descent: (self.line_height - self.base) * scale,
+            line_height: self.line_height * scale,
+        }
+    }
+
+    fn glyph(&mut self, _engine: &mut Engine, ch: char, size: f32) -> Option<Glyph> {
+        let glyph = self
+            .glyphs
+            .get(&ch)
+            .or_else(|| self.glyphs.get(&self.fallback?))?;
+        Some(glyph.scaled(size / self.size))
+    }
+
+    fn kerning(&self, left: char, right: char, size: f32) -> f32 {
+        self.kerning.get(&(left, right)).copied().unwrap_or(0.0) * size / self.size
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBitmapFont {
+    image: PathBuf,
+    line_height: f32,
+    base: Option<f32>,
+    size: Option<f32>,
+    fallback: Option<char>,
+    grid: Option<RawGrid>,
+    #[serde(default)]
+    glyphs: Vec<RawGlyph>,
+    #[serde(default)]
+    kerning: Vec<RawKerning>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGrid {
+    cell_width: u32,
+    cell_height: u32,
+    chars: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawGlyph {
+    char: char,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    x_offset: f32,
+    #[serde(default)]
+    y_offset: f32,
+    advance: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawKerning {
+    left: char,
+    right: char,
+    amount: f32,
+}
+
+impl Engine {
+    /// Loads a bitmap font described by a TOML glyph table and creates its image as the
+    /// texture `id`.
+    ///
+    /// ```toml
+    /// image = "font.png"  # relative to this file
+    /// line_height = 16
+    /// base = 13           # from the top of a line to the baseline, defaults to line_height
+    /// size = 16           # font size, defaults to line_height
+    /// fallback = "?"
+    ///
+    /// # a monospace grid ...
+    /// [grid]
+    /// cell_width = 8
+    /// cell_height = 16
+    /// chars = " !\"#$%&'()*+,-./0123456789"
+    ///
+    /// # ... and/or glyphs of any size
+    /// [[glyphs]]
+    /// char = "W"
+    /// x = 0
+    /// y = 32
+    /// width = 12
+    /// height = 16
+    /// x_offset = 0    # optional
+    /// y_offset = 0    # optional, from the top of the line
+    /// advance = 13    # optional, defaults to width
+    ///
+    /// [[kerning]]
+    /// left = "A"
+    /// right = "V"
+    /// amount = -1
+    /// ```
+    pub fn load_bitmap_font(
+        &mut self,
+        id: String,
+        path: impl AsRef<Path>,
+    ) -> Result<BitmapFont, EngineError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let raw: RawBitmapFont =
+            toml::from_str(&source).map_err(|err| EngineError::InvalidFont {
+                path: path.to_path_buf(),
+                message: err.to_string(),
+            })?;
+
+        let image_path = path.parent().unwrap_or(Path::new("")).join(&raw.image);
+        let texture = self.create_texture(id, image_path)?;
+
+        let base = raw.base.unwrap_or(raw.line_height);
+        let mut font = BitmapFont::new(raw.size.unwrap_or(raw.line_height), raw.line_height, base);
+        font.set_fallback(raw.fallback);
+        if let Some(grid) = raw.grid {
+            // just created
+            let (width, height) = self.texture_dimensions(texture).unwrap();
+            let sheet = SpriteSheet::from_grid(
+                texture,
+                width,
+                height,
+                SpriteSheetGrid::new(grid.cell_width, grid.cell_height),
+            );
+            for (ch, region) in grid.chars.chars().zip(sheet.frames()) {
+                font.add_glyph(
+                    ch,
+                    Some(*region),
+                    // a cell spans the whole line
+                    Vec2::ZERO,
+                    Vec2::new(grid.cell_width as f32, grid.cell_height as f32),
+                    grid.cell_width as f32,
+                );
+            }
+        }
+        for glyph in raw.glyphs {
+            let region = (glyph.width > 0 && glyph.height > 0).then(|| {
+                TextureRegion::from_pixels(texture, glyph.x, glyph.y, glyph.width, glyph.height)
+            });
+            font.add_glyph(
+                glyph.char,
+                region,
+                Vec2::new(glyph.x_offset, glyph.y_offset),
+                Vec2::new(glyph.width as f32, glyph.height as f32),
+                glyph.advance.unwrap_or(glyph.width as f32),
+            );
+        }
+        for kerning in raw.kerning {
+            font.add_kerning(kerning.left, kerning.right, kerning.amount);
+        }
+        Ok(font)
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::str::FromStr;
+
+use glam::Vec2;
+
+use crate::{BitmapFont, Engine, EngineError, TextureRegion};
+
+/// BMFont writes the glyph for characters the font doesn't have with id -1.
+const INVALID_CHAR_ID: &str = "-1";
+/// Where that glyph is kept, it's used as the font's fallback.
+const INVALID_CHAR: char = char::REPLACEMENT_CHARACTER;
+
+/// One line of the text format or one element of the XML format, e.g.
+/// `char id=65 x=10 y=0 ...`.
+struct Tag {
+    name: String,
+    attributes: HashMap<String, String>,
+}
+
+impl Tag {
+    fn get<T: FromStr>(&self, key: &str) -> Result<T, String> {
+        let value = self
+            .attributes
+            .get(key)
+            .ok_or_else(|| format!("`{}` is missing `{key}`", self.name))?;
+        value
+            .parse()
+            .map_err(|_| format!("`{}` has an invalid `{key}`: {value:?}", self.name))
+    }
+
+    fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        if self.attributes.contains_key(key) {
+            self.get(key)
+        } else {
+            Ok(default)
+        }
+    }
+}
+
+/// A `char` tag, read before the page textures it refers to are created.
+struct CharTag {
+    ch: char,
+    page_id: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    offset: Vec2,
+    advance: f32,
+}
+
+/// Reads either format, telling them apart by the XML's leading `<`.
+fn parse(source: &str) -> Result<Vec<Tag>, String> {
+    // some editors start UTF-8 files with a byte order mark
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    if source.trim_start().starts_with('<') {
+        parse_xml(source)
+    } else {
+        Ok(parse_text(source))
+    }
+}
+
+fn parse_text(source: &str) -> Vec<Tag> {
+    let mut tags = vec![];
+    for line in source.lines() {
+        let line = line.trim();
+        let (name, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if name.is_empty() {
+            continue;
+        }
+
+        let mut attributes = HashMap::new();
+        while let Some((key, after_key)) = rest.trim_start().split_once('=') {
+            // quoted values like `face="Open Sans"` can contain spaces
+            let (value, after_value) = match after_key.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+                None => after_key
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((after_key, "")),
+            };
+            attributes.insert(key.trim().to_string(), value.to_string());
+            rest = after_value;
+        }
+        tags.push(Tag {
+            name: name.to_string(),
+            attributes,
+        });
+    }
+    tags
+}
+
+fn parse_xml(source: &str) -> Result<Vec<Tag>, String> {
+    let document = roxmltree::Document::parse(source).map_err(|err| err.to_string())?;
+    let tags = document
+        .descendants()
+        .filter(roxmltree::Node::is_element)
+        .map(|node| Tag {
+            name: node.tag_name().name().to_string(),
+            attributes: node
+                .attributes()
+                .map(|attribute| (attribute.name().to_string(), attribute.value().to_string()))
+                .collect(),
+        })
+        .collect();
+    Ok(tags)
+}
+
+impl Engine {
+    /// Loads a font made with BMFont, Hiero or another tool writing AngelCode `.fnt` files, in
+    /// the text or the XML format. Every page is created as the texture `"{id}-{page}"`, the
+    /// page images are relative to the `.fnt` file.
+    ///
+    /// The binary format and fonts with glyphs packed into separate color channels aren't
+    /// supported.
+    pub fn load_bmfont(
+        &mut self,
+        id: String,
+        path: impl AsRef<Path>,
+    ) -> Result<BitmapFont, EngineError> {
+        let path = path.as_ref();
+        let invalid_font = |message: String| EngineError::InvalidFont {
+            path: path.to_path_buf(),
+            message,
+        };
+
+        let bytes = std::fs::read(path).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if bytes.starts_with(b"BMF") {
+            return Err(invalid_font(
+                "binary .fnt files aren't supported, export as text or XML".to_string(),
+            ));
+        }
+        let source = String::from_utf8(bytes).map_err(|err| invalid_font(err.to_string()))?;
+        let tags = parse(&source).map_err(invalid_font)?;
+
+        let find = |name: &str| tags.iter().find(|tag| tag.name == name);
+        let common = find("common").ok_or_else(|| invalid_font("no `common` tag".to_string()))?;
+        let line_height: f32 = common.get("lineHeight").map_err(invalid_font)?;
+        let base: f32 = common.get("base").map_err(invalid_font)?;
+        if common.get_or("packed", 0).map_err(invalid_font)? != 0 {
+            return Err(invalid_font(
+                "glyphs packed into color channels aren't supported".to_string(),
+            ));
+        }
+        // negative when the size was matched to the glyph height instead of the cell height
+        let size = match find("info") {
+            Some(info) => info
+                .get_or("size", line_height)
+                .map_err(invalid_font)?
+                .abs(),
+            None => line_height,
+        };
+
+        // Everything is read before the pages are created, and the pages are removed again if
+        // one of them fails to load, so a broken font doesn't leave textures behind.
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let mut page_files = BTreeMap::new();
+        for page in tags.iter().filter(|tag| tag.name == "page") {
+            let page_id: u32 = page.get("id").map_err(invalid_font)?;
+            let file: String = page.get("file").map_err(invalid_font)?;
+            page_files.insert(page_id, directory.join(file));
+        }
+
+        let mut chars = vec![];
+        let mut has_invalid_char = false;
+        for char_tag in tags.iter().filter(|tag| tag.name == "char") {
+            let ch = match char_tag.attributes.get("id").map(String::as_str) {
+                Some(INVALID_CHAR_ID) => {
+                    has_invalid_char = true;
+                    INVALID_CHAR
+                }
+                _ => {
+                    let char_id: u32 = char_tag.get("id").map_err(invalid_font)?;
+                    char::from_u32(char_id)
+                        .ok_or_else(|| invalid_font(format!("invalid char id {char_id}")))?
+                }
+            };
+            let page_id: u32 = char_tag.get_or("page", 0).map_err(invalid_font)?;
+            if !page_files.contains_key(&page_id) {
+                return Err(invalid_font(format!(
+                    "char {ch:?} is on missing page {page_id}"
+                )));
+            }
+            chars.push(CharTag {
+                ch,
+                page_id,
+                x: char_tag.get("x").map_err(invalid_font)?,
+                y: char_tag.get("y").map_err(invalid_font)?,
+                width: char_tag.get("width").map_err(invalid_font)?,
+                height: char_tag.get("height").map_err(invalid_font)?,
+                offset: Vec2::new(
+                    char_tag.get_or("xoffset", 0.0).map_err(invalid_font)?,
+                    char_tag.get_or("yoffset", 0.0).map_err(invalid_font)?,
+                ),
+                advance: char_tag.get("xadvance").map_err(invalid_font)?,
+            });
+        }
+
+        let mut kernings = vec![];
+        for kerning in tags.iter().filter(|tag| tag.name == "kerning") {
+            let first: u32 = kerning.get("first").map_err(invalid_font)?;
+            let second: u32 = kerning.get("second").map_err(invalid_font)?;
+            let amount: f32 = kerning.get("amount").map_err(invalid_font)?;
+            if let (Some(first), Some(second)) = (char::from_u32(first), char::from_u32(second)) {
+                kernings.push((first, second, amount));
+            }
+        }
+
+        let mut pages = HashMap::new();
+        for (page_id, image_path) in page_files {
+            match self.create_texture(format!("{id}-{page_id}"), image_path) {
+                Ok(texture) => {
+                    pages.insert(page_id, texture);
+                }
+                Err(err) => {
+                    for texture in pages.into_values() {
+                        self.remove_texture(texture);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        let mut font = BitmapFont::new(size, line_height, base);
+        if has_invalid_char {
+            font.set_fallback(Some(INVALID_CHAR));
+        }
+        // every char's page was checked to exist above
+        for char_tag in chars {
+            let region = (char_tag.width > 0 && char_tag.height > 0).then(|| {
+                TextureRegion::from_pixels(
+                    pages[&char_tag.page_id],
+                    char_tag.x,
+                    char_tag.y,
+                    char_tag.width,
+                    char_tag.height,
+                )
+            });
+            font.add_glyph(
+                char_tag.ch,
+                region,
+                char_tag.offset,
+                Vec2::new(char_tag.width as f32, char_tag.height as f32),
+                char_tag.advance,
+            );
+        }
+        for (first, second, amount) in kernings {
+            font.add_kerning(first, second, amount);
+        }
+        Ok(font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_attributes() {
+        let tags = parse_text("char id=65   x=10 y=0\n");
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "char");
+        assert_eq!(tags[0].get::<u32>("id"), Ok(65));
+        assert_eq!(tags[0].get::<u32>("x"), Ok(10));
+        assert_eq!(tags[0].get::<u32>("y"), Ok(0));
+    }
+
+    #[test]
+    fn quoted_values_keep_spaces() {
+        let tags = parse_text(r#"info face="Open Sans Bold" size=32 charset="""#);
+        assert_eq!(tags[0].attributes["face"], "Open Sans Bold");
+        assert_eq!(tags[0].get::<i32>("size"), Ok(32));
+        assert_eq!(tags[0].attributes["charset"], "");
+    }
+
+    #[test]
+    fn unterminated_quote_runs_to_end_of_line() {
+        let tags = parse_text("page id=0 file=\"font 0.png\nchars count=1");
+        assert_eq!(tags[0].attributes["file"], "font 0.png");
+        assert_eq!(tags[1].name, "chars");
+    }
+
+    #[test]
+    fn blank_lines_and_crlf() {
+        let tags = parse_text("common lineHeight=20\r\n\r\n  \r\nkerning first=65\r\n");
+        let names: Vec<_> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, ["common", "kerning"]);
+        assert_eq!(tags[0].get::<f32>("lineHeight"), Ok(20.0));
+        assert_eq!(tags[1].get::<u32>("first"), Ok(65));
+    }
+
+    #[test]
+    fn missing_and_invalid_attributes() {
+        let tags = parse_text("char id=abc");
+        assert_eq!(
+            tags[0].get::<u32>("x"),
+            Err("`char` is missing `x`".to_string())
+        );
+        assert_eq!(
+            tags[0].get::<u32>("id"),
+            Err("`char` has an invalid `id`: \"abc\"".to_string())
+        );
+        assert_eq!(tags[0].get_or("page", 0u32), Ok(0));
+    }
+
+    #[test]
+    fn byte_order_mark_is_skipped() {
+        let tags = parse("\u{feff}info size=16").unwrap();
+        assert_eq!(tags[0].name, "info");
+
+        let tags =
+            parse("\u{feff}<?xml version=\"1.0\"?><font><info size=\"16\"/></font>").unwrap();
+        let names: Vec<_> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(names, ["font", "info"]);
+        assert_eq!(tags[1].get::<i32>("size"), Ok(16));
+    }
+}
@@ -1 +1,39 @@
-// todo
+use glam::{Mat4, Vec2};
+
+/// Orthographic 2D camera. The origin `(0, 0)` is the bottom-left corner of the viewport and
+/// one unit is one logical pixel, so positions passed to `render_*` are in logical pixels.
+pub struct Camera {
+    pub position: Vec2,
+    viewport: Vec2,
+}
+
+impl Camera {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            position: Vec2::ZERO,
+            viewport: Vec2::new(width, height),
+        }
+    }
+
+    /// Called by the runner whenever the window changes size or scale factor.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        if width > 0.0 && height > 0.0 {
+            self.viewport = Vec2::new(width, height);
+        }
+    }
+
+    pub fn viewport(&self) -> Vec2 {
+        self.viewport
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        Mat4::orthographic_lh(
+            self.position.x,
+            self.position.x + self.viewport.x,
+            self.position.y,
+            self.position.y + self.viewport.y,
+            -1.0,
+            1.0,
+        )
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use winit::event_loop::EventLoop;
+use winit::keyboard::KeyCode;
+use winit::monitor::{MonitorHandle, VideoMode};
+use winit::window::{Fullscreen, WindowBuilder};
+
+use crate::error::EngineError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    Windowed,
+    BorderlessFullscreen,
+    /// Takes over the monitor using its best video mode, or the one matching `width` x `height` if there is any.
+    ExclusiveFullscreen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorSelection {
+    Primary,
+    /// Index into the list of monitors reported by the OS. Falls back to the primary monitor when out of range.
+    Index(usize),
+}
+
+/// Which graphics APIs wgpu is allowed to use. Overridden by the `WGPU_BACKEND` environment variable
+/// (e.g. `WGPU_BACKEND=gl`), which accepts a comma separated list of `vulkan`, `dx12`, `metal`, `gl`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// Vulkan, Metal or DX12.
+    Primary,
+    /// OpenGL / GLES, for machines without a modern driver.
+    Gl,
+    All,
+}
+
+impl Backend {
+    pub(crate) fn backends(self) -> wgpu::Backends {
+        if let Some(backends) = wgpu::util::backend_bits_from_env() {
+            return backends;
+        }
+
+        match self {
+            Backend::Primary => wgpu::Backends::PRIMARY,
+            Backend::Gl => wgpu::Backends::GL,
+            Backend::All => wgpu::Backends::all(),
+        }
+    }
+}
+
+/// Mirrors `wgpu::PresentMode` so it can be read from a config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentMode {
+    AutoVsync,
+    AutoNoVsync,
+    Fifo,
+    FifoRelaxed,
+    Immediate,
+    Mailbox,
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::AutoVsync => wgpu::PresentMode::AutoVsync,
+            PresentMode::AutoNoVsync => wgpu::PresentMode::AutoNoVsync,
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::FifoRelaxed => wgpu::PresentMode::FifoRelaxed,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+        }
+    }
+}
+
+/// Window and engine startup options, passed to `async_runner_with_config`.
+///
+/// Every field is optional in a config file, missing ones take the value from `EngineConfig::default()`:
+///
+/// ```toml
+/// title = "Level editor"
+/// width = 1280
+/// height = 720
+/// window_mode = "windowed"
+/// monitor = { index = 1 }
+/// present_mode = "mailbox"
+/// backend = "gl"
+/// quit_key = "Escape"
+/// texture_hot_reload = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub title: String,
+    /// Logical size of the window when windowed.
+    pub width: u32,
+    pub height: u32,
+    pub window_mode: WindowMode,
+    pub monitor: MonitorSelection,
+    pub resizable: bool,
+    pub present_mode: PresentMode,
+    pub max_frame_latency: u32,
+    pub backend: Backend,
+    /// Only accept a software rasterizer (e.g. lavapipe, WARP or llvmpipe).
+    pub force_fallback_adapter: bool,
+    /// Pressing this key exits the game, same as closing the window. Disabled when `None`.
+    pub quit_key: Option<KeyCode>,
+    /// Reloads textures created from files when the files change on disk, see
+    /// `Engine::set_texture_hot_reload`.
+    pub texture_hot_reload: bool,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("Game"),
+            width: 800,
+            height: 600,
+            window_mode: WindowMode::BorderlessFullscreen,
+            monitor: MonitorSelection::Primary,
+            resizable: true,
+            present_mode: PresentMode::Fifo,
+            max_frame_latency: 2,
+            backend: Backend::Primary,
+            force_fallback_adapter: false,
+            quit_key: None,
+            texture_hot_reload: false,
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn from_toml_str(source: &str) -> Result<Self, EngineError> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, EngineError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&source)
+    }
+
+    pub(crate) fn window_builder(&self, event_loop: &EventLoop<()>) -> WindowBuilder {
+        let monitor = self.select_monitor(event_loop);
+
+        let builder = WindowBuilder::new()
+            .with_title(self.title.clone())
+            .with_resizable(self.resizable)
+            .with_inner_size(winit::dpi::LogicalSize::new(self.width, self.height));
+
+        match self.window_mode {
+            WindowMode::Windowed => match monitor {
+                Some(monitor) => builder.with_position(monitor.position()),
+                None => builder,
+            },
+            WindowMode::BorderlessFullscreen => {
+                builder.with_fullscreen(Some(Fullscreen::Borderless(monitor)))
+            }
+            WindowMode::ExclusiveFullscreen => {
+                match monitor.and_then(|monitor| self.select_video_mode(&monitor)) {
+                    Some(video_mode) => {
+                        builder.with_fullscreen(Some(Fullscreen::Exclusive(video_mode)))
+                    }
+                    None => {
+                        log::warn!(
+                            "No video mode available for exclusive fullscreen, using borderless"
+                        );
+                        builder.with_fullscreen(Some(Fullscreen::Borderless(None)))
+                    }
+                }
+            }
+        }
+    }
+
+    fn select_monitor(&self, event_loop: &EventLoop<()>) -> Option<MonitorHandle> {
+        match self.monitor {
+            MonitorSelection::Primary => event_loop.primary_monitor(),
+            MonitorSelection::Index(index) => {
+                event_loop.available_monitors().nth(index).or_else(|| {
+                    log::warn!("Monitor {} not found, using the primary monitor", index);
+                    event_loop.primary_monitor()
+                })
+            }
+        }
+    }
+
+    fn select_video_mode(&self, monitor: &MonitorHandle) -> Option<VideoMode> {
+        let scale_factor = monitor.scale_factor();
+        let requested =
+            winit::dpi::LogicalSize::new(self.width, self.height).to_physical::<u32>(scale_factor);
+
+        let best = |a: &VideoMode, b: &VideoMode| {
+            let a_size = a.size();
+            let b_size = b.size();
+            (a_size.width * a_size.height, a.refresh_rate_millihertz())
+                .cmp(&(b_size.width * b_size.height, b.refresh_rate_millihertz()))
+        };
+
+        monitor
+            .video_modes()
+            .filter(|mode| mode.size() == requested)
+            .max_by(best)
+            .or_else(|| monitor.video_modes().max_by(best))
+    }
+}
@@ -1,61 +1,110 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use crate::texture::Texture;
+use crate::config::EngineConfig;
+use crate::error::EngineError;
 
 pub struct AppContext {
     pub surface: wgpu::Surface<'static>,
-    pub config: wgpu::SurfaceConfiguration,
+    // The context is shared behind an `Arc`, so everything that changes on resize lives behind a `Mutex`.
+    pub config: Mutex<wgpu::SurfaceConfiguration>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
-    pub size: winit::dpi::PhysicalSize<u32>,
-    pub texture: Texture,
+    pub size: Mutex<winit::dpi::PhysicalSize<u32>>,
     pub render_texture_format: wgpu::TextureFormat,
+    // Subset of the optional features the adapter actually supports.
+    pub features: wgpu::Features,
 }
 
+/// Needed to draw quads with different textures in one call, see `BindlessTextures`.
+pub(crate) const BINDLESS_TEXTURE_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_BINDING_ARRAY
+    .union(wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING);
+
+/// Features the engine can make use of but doesn't need in order to run.
+const OPTIONAL_FEATURES: wgpu::Features =
+    wgpu::Features::POLYGON_MODE_LINE.union(BINDLESS_TEXTURE_FEATURES);
+
 impl AppContext {
-    pub async fn new(main_window: Arc<winit::window::Window>) -> anyhow::Result<Self> {
+    pub async fn new(
+        main_window: Arc<winit::window::Window>,
+        engine_config: &EngineConfig,
+    ) -> Result<Self, EngineError> {
         let size = main_window.inner_size();
 
+        let backends = engine_config.backend.backends();
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN,
-            dx12_shader_compiler: Default::default(),
+            backends,
+            dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
             flags: Default::default(),
-            gles_minor_version: Default::default(),
+            gles_minor_version: wgpu::util::gles_minor_version_from_env().unwrap_or_default(),
         });
 
         let surface = instance.create_surface(main_window.clone())?;
 
-        let mut required_features = wgpu::Features::empty();
-        required_features.insert(wgpu::Features::POLYGON_MODE_LINE);
-        required_features.insert(wgpu::Features::TEXTURE_BINDING_ARRAY);
-
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
                 compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
+                force_fallback_adapter: engine_config.force_fallback_adapter,
             })
-            .await
-            .unwrap();
-        dbg!("adapter is: {:?}", &adapter.get_info());
+            .await;
+
+        let Some(adapter) = adapter else {
+            let available: Vec<String> = instance
+                .enumerate_adapters(wgpu::Backends::all())
+                .iter()
+                .map(|adapter| {
+                    let info = adapter.get_info();
+                    format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type)
+                })
+                .collect();
+
+            return Err(EngineError::NoSuitableAdapter {
+                backends,
+                force_fallback_adapter: engine_config.force_fallback_adapter,
+                available,
+            });
+        };
+        log::info!("adapter is: {:?}", adapter.get_info());
+
+        let required_features = OPTIONAL_FEATURES & adapter.features();
+        let missing_features = OPTIONAL_FEATURES - required_features;
+        if !missing_features.is_empty() {
+            log::warn!(
+                "Adapter doesn't support {:?}, running without them",
+                missing_features
+            );
+        }
+
+        // Software and GL adapters often can't reach the default limits.
+        let mut required_limits = if cfg!(target_arch = "wasm32") {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        } else if adapter.get_downlevel_capabilities().is_webgpu_compliant() {
+            wgpu::Limits::default()
+        } else {
+            wgpu::Limits::downlevel_defaults()
+        }
+        .using_resolution(adapter.limits());
+        // The more textures fit in a binding array, the fewer draw calls.
+        if required_features.contains(BINDLESS_TEXTURE_FEATURES) {
+            let adapter_limits = adapter.limits();
+            required_limits.max_sampled_textures_per_shader_stage =
+                adapter_limits.max_sampled_textures_per_shader_stage;
+            required_limits.max_samplers_per_shader_stage =
+                adapter_limits.max_samplers_per_shader_stage;
+        }
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
                     required_features,
-                    required_limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
+                    required_limits,
                 },
                 None,
             )
-            .await
-            .unwrap();
+            .await?;
 
-        dbg!("device is: {:?}", &device);
+        log::info!("device is: {:?}", &device);
         let surface_caps = surface.get_capabilities(&adapter);
 
         let surface_format = surface_caps
@@ -65,32 +114,44 @@ impl AppContext {
             .find(|format| format.is_srgb())
             .unwrap_or(wgpu::TextureFormat::Bgra8UnormSrgb);
 
+        // The Auto* modes always resolve to something supported, the explicit ones might not exist on this surface.
+        let mut present_mode = engine_config.present_mode.into();
+        if !matches!(
+            present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) && !surface_caps.present_modes.contains(&present_mode)
+        {
+            log::warn!(
+                "Present mode {:?} is not supported by the surface, falling back to Fifo",
+                present_mode
+            );
+            present_mode = wgpu::PresentMode::Fifo;
+        }
+
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode,
             // present_mode: surface_caps.present_modes[1],
-            desired_maximum_frame_latency: 2,
+            desired_maximum_frame_latency: engine_config.max_frame_latency,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
 
         let render_texture_format = surface_format;
 
-        let texture = Texture::from_dimensions(&device, &config, Some("source_texture")).unwrap();
-
         surface.configure(&device, &config);
 
         Ok(Self {
             device,
             queue,
             surface,
-            config,
-            size,
-            texture,
+            config: Mutex::new(config),
+            size: Mutex::new(size),
             render_texture_format,
+            features: required_features,
         })
     }
 
@@ -103,15 +164,28 @@ impl AppContext {
     }
 
     pub fn reconfigure_surface(&self) {
-        self.surface.configure(&self.device, &self.config);
+        let config = self.config.lock().unwrap();
+        self.surface.configure(&self.device, &config);
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.reconfigure_surface();
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        *self.size.lock().unwrap()
+    }
+
+    /// Reconfigures the surface.
+    /// Returns `false` when the new size is empty (e.g. the window got minimized) and nothing was done.
+    pub fn resize(&self, new_size: winit::dpi::PhysicalSize<u32>) -> bool {
+        if new_size.width == 0 || new_size.height == 0 {
+            return false;
+        }
+
+        *self.size.lock().unwrap() = new_size;
+        {
+            let mut config = self.config.lock().unwrap();
+            config.width = new_size.width;
+            config.height = new_size.height;
         }
+        self.reconfigure_surface();
+        true
     }
 }
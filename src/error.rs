@@ -0,0 +1,86 @@
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EngineError {
+    #[error("Couldn't create the event loop: {0}")]
+    EventLoop(#[from] winit::error::EventLoopError),
+
+    #[error("Couldn't create the window: {0}")]
+    Window(#[from] winit::error::OsError),
+
+    #[error("Couldn't create the surface: {0}")]
+    CreateSurface(#[from] wgpu::CreateSurfaceError),
+
+    #[error(
+        "No suitable graphics adapter for backends {backends:?} (force_fallback_adapter: {force_fallback_adapter}). Available adapters: [{}]",
+        available.join(", ")
+    )]
+    NoSuitableAdapter {
+        backends: wgpu::Backends,
+        force_fallback_adapter: bool,
+        available: Vec<String>,
+    },
+
+    #[error("Couldn't request a device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+
+    #[error("Invalid config: {0}")]
+    Config(#[from] toml::de::Error),
+
+    #[error("Invalid Aseprite sheet {}: {source}", path.display())]
+    Aseprite {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("Couldn't read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Couldn't decode {}: {source}", path.display())]
+    ImageDecode {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    #[error(
+        "Couldn't load {}: {format:?} images aren't supported{}",
+        path.display(),
+        feature.map(|feature| format!(", enable the `{}` feature", feature)).unwrap_or_default()
+    )]
+    UnsupportedImageFormat {
+        path: PathBuf,
+        format: image::ImageFormat,
+        // cargo feature that adds support for it
+        feature: Option<&'static str>,
+    },
+
+    #[error("Invalid font {}: {message}", path.display())]
+    InvalidFont { path: PathBuf, message: String },
+
+    #[error("Texture update rect {rect:?} doesn't fit in the {width}x{height} texture")]
+    TextureUpdateOutOfBounds {
+        // [x, y, width, height]
+        rect: [u32; 4],
+        width: u32,
+        height: u32,
+    },
+
+    #[error("Expected {expected} bytes of RGBA data for the texture, got {actual}")]
+    TextureDataSize { expected: usize, actual: usize },
+
+    #[error("Textures have to be between 1x1 and {max}x{max}, not {width}x{height}")]
+    InvalidTextureSize { width: u32, height: u32, max: u32 },
+
+    #[error(
+        "Image '{name}' ({width}x{height}) doesn't fit in a {page_size}x{page_size} atlas page"
+    )]
+    AtlasImageTooLarge {
+        name: String,
+        width: u32,
+        height: u32,
+        page_size: u32,
+    },
+}
@@ -0,0 +1,106 @@
+use glam::{Vec2, Vec3};
+
+use crate::{Engine, TextureRegion};
+
+/// A glyph scaled to the size it was requested at. All values are in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    /// `None` for glyphs with nothing to draw, like spaces.
+    pub region: Option<TextureRegion>,
+    /// From the pen position on the baseline to the glyph's top-left corner, y going down.
+    pub offset: Vec2,
+    pub size: Vec2,
+    /// How far the pen moves after this glyph.
+    pub advance: f32,
+}
+
+impl Glyph {
+    pub(crate) fn scaled(self, scale: f32) -> Self {
+        Self {
+            offset: self.offset * scale,
+            size: self.size * scale,
+            advance: self.advance * scale,
+            ..self
+        }
+    }
+}
+
+/// Vertical metrics of a font at some size, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    /// From the top of a line to the baseline.
+    pub ascent: f32,
+    /// From the baseline to the bottom of a line, positive.
+    pub descent: f32,
+    /// From one baseline to the next.
+    pub line_height: f32,
+}
+
+/// Something `Engine::render_text` can draw with. `size` is always the font size in pixels.
+pub trait Font {
+    fn line_metrics(&self, size: f32) -> LineMetrics;
+
+    /// `None` if the font can't draw `ch`. Gets the engine so fonts can create or update their
+    /// textures on demand.
+    fn glyph(&mut self, engine: &mut Engine, ch: char, size: f32) -> Option<Glyph>;
+
+    /// Extra space between `left` and `right`, usually negative.
+    fn kerning(&self, _left: char, _right: char, _size: f32) -> f32 {
+        0.0
+    }
+}
+
+impl Engine {
+    /// Draws `text` with its top-left corner at `position`, starting a new line at every `\n`
+    /// or `\r\n`.
+    /// Glyphs go through the quad pipeline, a font on one texture costs a single draw call.
+    /// `layout_text` adds wrapping, alignment and inline styling.
+    pub fn render_text(
+        &mut self,
+        text: &str,
+        position: Vec3,
+        font: &mut (impl Font + ?Sized),
+        size: f32,
+        color: [f32; 4],
+    ) {
+        let metrics = font.line_metrics(size);
+        let mut baseline = position.y - metrics.ascent;
+        for line in text.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let mut pen_x = position.x;
+            let mut previous = None;
+            for ch in line.chars() {
+                if let Some(previous) = previous {
+                    pen_x += font.kerning(previous, ch, size);
+                }
+                previous = Some(ch);
+                let Some(glyph) = font.glyph(self, ch, size) else {
+                    continue;
+                };
+                self.push_glyph(&glyph, Vec3::new(pen_x, baseline, position.z), color);
+                pen_x += glyph.advance;
+            }
+            baseline -= metrics.line_height;
+        }
+    }
+
+    /// `pen` is on the baseline.
+    pub(crate) fn push_glyph(&mut self, glyph: &Glyph, pen: Vec3, color: [f32; 4]) {
+        let Some(region) = glyph.region else {
+            return;
+        };
+        let center = Vec3::new(
+            pen.x + glyph.offset.x + glyph.size.x / 2.0,
+            pen.y - glyph.offset.y - glyph.size.y / 2.0,
+            pen.z,
+        );
+        self.push_quad(
+            center,
+            glyph.size.extend(1.0),
+            0.0,
+            color,
+            Some(region.texture),
+            region.rect,
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::codecs::gif::GifDecoder;
+use image::error::{LimitError, LimitErrorKind};
+use image::{AnimationDecoder, GenericImage, ImageError, RgbaImage};
+
+use crate::{
+    Animation, AnimationFrame, Engine, EngineError, PlaybackMode, SpriteSheet, SpriteSheetGrid,
+};
+
+/// Browsers show frames without a delay for 100ms, so do we.
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
+impl Engine {
+    /// Loads every frame of an animated GIF into one texture called `id`, laid out as a grid,
+    /// and returns a looping animation with the GIF's frame delays.
+    /// `create_texture` only loads the first frame.
+    pub fn load_gif_animation(
+        &mut self,
+        id: String,
+        path: impl AsRef<Path>,
+    ) -> Result<Animation, EngineError> {
+        let path = path.as_ref();
+        let decode_error = |source| EngineError::ImageDecode {
+            path: path.to_path_buf(),
+            source,
+        };
+        let bytes = std::fs::read(path).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let frames = GifDecoder::new(Cursor::new(bytes))
+            .and_then(|decoder| decoder.into_frames().collect_frames())
+            .map_err(decode_error)?;
+        let Some(first) = frames.first() else {
+            return Ok(Animation::new(vec![], PlaybackMode::Loop));
+        };
+
+        // Every frame is composited to the full canvas size.
+        let (width, height) = first.buffer().dimensions();
+        let max_dimension = self.max_texture_dimension();
+        let columns = (frames.len() as f32).sqrt().ceil() as u32;
+        let columns = columns.min(max_dimension / width.max(1)).max(1);
+        let rows = (frames.len() as u32).div_ceil(columns);
+        if columns * width > max_dimension || rows * height > max_dimension {
+            return Err(decode_error(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::DimensionError,
+            ))));
+        }
+
+        let mut grid_image = RgbaImage::new(columns * width, rows * height);
+        for (i, frame) in frames.iter().enumerate() {
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            grid_image
+                .copy_from(frame.buffer(), column * width, row * height)
+                .map_err(decode_error)?;
+        }
+
+        let texture = self.create_texture_from_image(id, &grid_image.into())?;
+        let sheet = SpriteSheet::from_grid(
+            texture,
+            columns * width,
+            rows * height,
+            SpriteSheetGrid::new(width, height),
+        );
+        let animation_frames = frames
+            .iter()
+            .zip(sheet.frames())
+            .map(|(frame, region)| {
+                let (numerator, denominator) = frame.delay().numer_denom_ms();
+                let duration = numerator as f32 / denominator.max(1) as f32 / 1000.0;
+                let duration = if duration > 0.0 {
+                    duration
+                } else {
+                    DEFAULT_FRAME_DURATION
+                };
+                AnimationFrame::new(*region, duration)
+            })
+            .collect();
+        Ok(Animation::new(animation_frames, PlaybackMode::Loop))
+    }
+}
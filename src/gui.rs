@@ -1,10 +1,7 @@
 use std::sync::Arc;
 
-use egui::{ClippedPrimitive, Context, RichText, TexturesDelta};
+use egui::{ClippedPrimitive, Context, TexturesDelta};
 use egui_wgpu::{Renderer, ScreenDescriptor};
-use wgpu;
-
-use winit::event_loop::EventLoopWindowTarget;
 use winit::window::Window;
 
 use crate::context::AppContext;
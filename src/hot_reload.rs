@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::texture::load_image;
+use crate::{Engine, EngineError, TextureHandle};
+
+/// How often the source files are checked. Stat-ing every texture each frame is wasteful.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls the modification time of the files textures were loaded from.
+pub(crate) struct TextureWatcher {
+    last_poll: Instant,
+    // last modification time seen per texture
+    modified: HashMap<TextureHandle, SystemTime>,
+}
+
+impl TextureWatcher {
+    fn new() -> Self {
+        Self {
+            last_poll: Instant::now(),
+            modified: HashMap::new(),
+        }
+    }
+
+    /// Called when the texture is removed.
+    pub(crate) fn forget(&mut self, handle: TextureHandle) {
+        self.modified.remove(&handle);
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl Engine {
+    /// Turns texture hot reloading on or off. While on, textures created from files
+    /// (`create_texture` and friends) are reloaded in place when their file changes, so
+    /// handles keep working. Meant for development, it's off by default.
+    pub fn set_texture_hot_reload(&mut self, enabled: bool) {
+        match (enabled, &self.texture_watcher) {
+            (true, None) => {
+                let mut watcher = TextureWatcher::new();
+                for (handle, source) in self.textures.sources() {
+                    if let Some(modified) = modified_time(source) {
+                        watcher.modified.insert(handle, modified);
+                    }
+                }
+                self.texture_watcher = Some(watcher);
+            }
+            (false, Some(_)) => self.texture_watcher = None,
+            _ => (),
+        }
+    }
+
+    pub fn texture_hot_reload(&self) -> bool {
+        self.texture_watcher.is_some()
+    }
+
+    /// Reads the texture's file again and replaces it in place, keeping its options.
+    /// Does nothing for textures that weren't created from a file.
+    pub fn reload_texture(&mut self, handle: TextureHandle) -> Result<(), EngineError> {
+        let Some(source) = self.textures.source(handle).map(Path::to_path_buf) else {
+            return Ok(());
+        };
+
+        let image = load_image(&source)?;
+        // both exist, the texture has a source
+        let id = self.textures.name(handle).unwrap().to_string();
+        let options = self.textures.get(handle).unwrap().options;
+        self.upload_image(id, &image, options, Some(source))?;
+        Ok(())
+    }
+
+    /// Called once per frame by the runner.
+    pub(crate) fn poll_texture_changes(&mut self) {
+        let Some(watcher) = &mut self.texture_watcher else {
+            return;
+        };
+        if watcher.last_poll.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        watcher.last_poll = Instant::now();
+
+        let mut changed = vec![];
+        for (handle, source) in self.textures.sources() {
+            let Some(modified) = modified_time(source) else {
+                continue;
+            };
+            // Textures created after hot reloading was turned on are only recorded.
+            if let Some(previous) = watcher.modified.insert(handle, modified) {
+                if previous != modified {
+                    changed.push(handle);
+                }
+            }
+        }
+
+        for handle in changed {
+            match self.reload_texture(handle) {
+                Ok(()) => log::info!(
+                    "Reloaded texture '{}'",
+                    self.textures.name(handle).unwrap_or_default()
+                ),
+                // Likely caught the file half written, the next write triggers another reload.
+                Err(err) => log::warn!("Couldn't reload texture: {}", err),
+            }
+        }
+    }
+}
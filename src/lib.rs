@@ -1,19 +1,16 @@
 use glam::*;
 
-use std::collections::HashMap;
 use std::collections::HashSet;
-use std::fmt::{Debug, Formatter, Pointer, Write};
-use std::fs;
-use std::num::NonZeroU32;
-use std::path::Path;
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
-use std::sync::Mutex;
 use wgpu::RenderPass;
 
 mod gui;
+mod hot_reload;
 
 use winit::event::KeyEvent;
-use winit::keyboard::KeyCode;
 
 use winit::event::DeviceEvent;
 use winit::event::ElementState;
@@ -21,19 +18,62 @@ use winit::{
     event::{Event, WindowEvent},
     event_loop::*,
     keyboard::PhysicalKey,
-    window::WindowBuilder,
 };
 
+mod animation;
+mod aseprite;
+mod atlas;
+mod bindless;
+mod bitmap_font;
+mod bmfont;
+mod camera;
+mod config;
 mod context;
+mod error;
+mod font;
+#[cfg(feature = "gif")]
+mod gif_animation;
+mod mipmap;
+mod nine_slice;
 mod pipeline;
+mod sprite;
+mod text_layout;
 mod texture;
-use texture::Texture;
+mod texture_loader;
+mod truetype_font;
+use texture::{load_image, Texture, TextureStore};
 
+pub use crate::animation::{
+    Animation, AnimationEvent, AnimationFrame, AnimationPlayer, PlaybackMode,
+};
+pub use crate::aseprite::{
+    AsepriteDirection, AsepriteFrame, AsepriteSheet, AsepriteSlice, AsepriteSliceKey, AsepriteTag,
+};
+pub use crate::atlas::{TextureAtlas, TextureAtlasBuilder};
+pub use crate::bitmap_font::BitmapFont;
+pub use crate::camera::Camera;
+pub use crate::config::{Backend, EngineConfig, MonitorSelection, PresentMode, WindowMode};
 use crate::context::AppContext;
+pub use crate::error::EngineError;
+pub use crate::font::{Font, Glyph, LineMetrics};
+pub use crate::nine_slice::{BorderInsets, NineSlice, NineSliceMode};
+pub use crate::sprite::{
+    RegionRect, SpriteDraw, SpriteSheet, SpriteSheetGrid, TextureRegion, UvTransform,
+};
+pub use crate::text_layout::{LaidOutText, TextAlign, TextLayout, VerticalAlign};
+pub use crate::texture::{
+    SamplerOptions, SharedTexture, TextureHandle, TextureOptions, TextureStats,
+};
+pub use crate::texture_loader::TextureLoadProgress;
+pub use crate::truetype_font::TrueTypeFont;
 
+use crate::bindless::BindlessTextures;
 use crate::gui::Framework;
+use crate::hot_reload::TextureWatcher;
+use crate::mipmap::MipmapGenerator;
 use crate::pipeline::RenderPipelineBuilder;
 use crate::pipeline::VertexDescriptor;
+use crate::texture_loader::TextureLoader;
 
 use wgpu::util::{align_to, BufferInitDescriptor, DeviceExt};
 
@@ -42,6 +82,17 @@ pub trait Application {
     fn on_update(&mut self, engine: &mut Engine, delta_time: f32);
     fn on_render(&mut self, engine: &mut Engine);
     fn on_event(&mut self, engine: &mut Engine, event: MyEvent);
+
+    /// Called once right before the event loop exits, either because the window was closed or
+    /// `Engine::request_exit` was called. Last chance to save state.
+    fn on_shutdown(&mut self, _engine: &mut Engine) {}
+
+    fn on_focus_changed(&mut self, _engine: &mut Engine, _focused: bool) {}
+
+    /// The OS took the app to the background (mostly mobile). Rendering stops until `on_resume`.
+    fn on_suspend(&mut self, _engine: &mut Engine) {}
+
+    fn on_resume(&mut self, _engine: &mut Engine) {}
 }
 
 struct TransformComponent {
@@ -323,7 +374,7 @@ impl CirclePipeline {
         let render_pipeline = RenderPipelineBuilder::new()
             .add_vertex_buffer_layout::<Vertex>()
             .add_color_target_state(wgpu::ColorTargetState {
-                format: app_context.config.format,
+                format: app_context.render_texture_format,
                 blend: Some(wgpu::BlendState {
                     color: wgpu::BlendComponent {
                         src_factor: wgpu::BlendFactor::SrcAlpha,
@@ -565,7 +616,7 @@ impl LinePipeline {
             .with_topology(wgpu::PrimitiveTopology::LineList)
             .add_vertex_buffer_layout::<Vertex>()
             .add_color_target_state(wgpu::ColorTargetState {
-                format: app_context.config.format,
+                format: app_context.render_texture_format,
                 blend: Some(wgpu::BlendState {
                     color: wgpu::BlendComponent::REPLACE,
                     alpha: wgpu::BlendComponent::REPLACE,
@@ -602,38 +653,107 @@ impl LinePipeline {
 // LINE
 
 // QUAD
+
+/// Used for quads without a texture.
+const WHITE_TEXTURE_ID: &str = "1px-white";
+/// Drawn instead of textures that don't exist, a magenta and black checkerboard that is hard to miss.
+const PLACEHOLDER_TEXTURE_ID: &str = "placeholder";
+
 struct QuadInfo {
     // updated for every `draw_quad`
-    transform: TransformComponent,
-    color: [f32; 4],
-    texture_name: Option<String>,
+    model: Mat4,
+    // top-left, top-right, bottom-right, bottom-left
+    corner_colors: [[f32; 4]; 4],
+    texture: Option<TextureHandle>,
+    // Sub-rectangle of the texture to sample, pixel rects are normalized in `update_quad_data`.
+    region: RegionRect,
+    // repeat and scroll inside `region`
+    uv_transform: UvTransform,
+    flip_x: bool,
+    flip_y: bool,
     // texture_path: Option<&'static Path>,
     // texture_path: Option<&'static [u8]>,
 }
 
+// Per quad data. Quads are drawn instanced so consecutive quads sharing a texture cost a single draw call.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct QuadInstance {
+    model_mat4: [[f32; 4]; 4],
+    // top-left, top-right, bottom-right, bottom-left
+    corner_colors: [[f32; 4]; 4],
+    uv_rect: [f32; 4],
+    // index into the bind group's texture array, 0 without bindless textures
+    texture_index: u32,
+    // repeat x, y, offset x, y
+    uv_transform: [f32; 4],
+}
+
+impl<'a> VertexDescriptor<'a> for QuadInstance {
+    fn desc() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 11] = wgpu::vertex_attr_array![
+            // model_mat4, one column per location
+            2 => Float32x4,
+            3 => Float32x4,
+            4 => Float32x4,
+            5 => Float32x4,
+            // corner_colors
+            6 => Float32x4,
+            7 => Float32x4,
+            8 => Float32x4,
+            9 => Float32x4,
+            // uv_rect
+            10 => Float32x4,
+            // texture_index
+            11 => Uint32,
+            // uv_transform
+            12 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<QuadInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Consecutive quads drawn with one call.
+struct QuadBatch {
+    instances: std::ops::Range<u32>,
+    binding: QuadBatchBinding,
+}
+
+enum QuadBatchBinding {
+    // the texture's own bind group
+    Texture(TextureHandle),
+    // a bind group of `BindlessTextures`
+    Array(usize),
+}
+
 struct QuadPipeline {
     quad_info: Vec<QuadInfo>,
+    // built by `update_quad_data`
+    batches: Vec<QuadBatch>,
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
 
-    // Uniforms
-    color_buffer: wgpu::Buffer,
-    model_mat4_buffer: wgpu::Buffer,
-
-    // Uniform alignments
-    transform_uniform_alignment: wgpu::BufferAddress,
-    color_uniform_alignment: wgpu::BufferAddress,
-
-    // This `wgpu::BindGroup`` holds uniform data for the quad that can change every frame
-    bind_group: wgpu::BindGroup,
+    // Instances, grows when a frame has more quads than it can hold
+    instance_buffer: wgpu::Buffer,
+    instance_capacity: usize,
 
     // Here I don't need a `wgpu::BindGroup` as this bindgroup is associated to a texture, not a buffer.
     // So in order to keep remapping textures I need to create one `wgpu::BindGroup` for each texture
     // and remember the `wgpu::BindGroupLayout`
     texture_bind_group_layout: wgpu::BindGroupLayout,
+
+    // `None` without device support, quads are then batched per texture
+    bindless: Option<BindlessTextures>,
 }
 
 impl QuadPipeline {
+    const INITIAL_INSTANCE_CAPACITY: usize = 64;
+
     fn new(app_context: Arc<AppContext>) -> Self {
         // vertex
         #[repr(C)]
@@ -666,33 +786,6 @@ impl QuadPipeline {
 
         // local to the model
         const VERTICES: &[Vertex] = &[
-            // Position of quad at the top-right corner
-            // Vertex {
-            //     position: [0.0, 0.0, 0.0],
-            //     tex_coords: [1.0, 0.0],
-            // },
-            // Vertex {
-            //     position: [-1.0, 0.0, 0.0],
-            //     tex_coords: [0.0, 0.0],
-            // },
-            // Vertex {
-            //     position: [-1.0, -1.0, 0.0],
-            //     tex_coords: [0.0, 1.0],
-            // },
-            // Vertex {
-            //     position: [-1.0, -1.0, 0.0],
-            //     tex_coords: [0.0, 1.0],
-            // },
-            // Vertex {
-            //     position: [0.0, -1.0, 0.0],
-            //     tex_coords: [1.0, 1.0],
-            // },
-            // Vertex {
-            //     position: [0.0, 0.0, 0.0],
-            //     tex_coords: [1.0, 0.0],
-            // },
-            // Position of quad at the top-right corner
-
             // Position of quad at the center
             Vertex {
                 position: [0.5, 0.5, 0.0],
@@ -730,105 +823,8 @@ impl QuadPipeline {
                     usage: wgpu::BufferUsages::VERTEX,
                 });
 
-        // textures
-        let diffuse_bytes = include_bytes!("happy-tree.png");
-        let diffuse_texture = Texture::from_bytes(
-            &app_context.device,
-            &app_context.queue,
-            diffuse_bytes,
-            "Happy tree",
-        )
-        .unwrap();
-
-        // let diffuse_texture = {
-        //     let rgba = img.to_rgba8();
-        //     let dimensions = img.dimensions();
-
-        //     let texture_extent = wgpu::Extent3d {
-        //         width: dimensions.0,
-        //         height: dimensions.1,
-        //         depth_or_array_layers: 1,
-        //     };
-        //     let texture = device.create_texture(&wgpu::TextureDescriptor {
-        //         label,
-        //         size: texture_extent,
-        //         mip_level_count: 1,
-        //         sample_count: 1,
-        //         dimension: wgpu::TextureDimension::D2,
-        //         format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        //         view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-        //         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-        //     });
-
-        //     // dimensions are 256 x 256 for `happy-tree.png`
-        //     queue.write_texture(
-        //         wgpu::ImageCopyTexture {
-        //             aspect: wgpu::TextureAspect::All,
-        //             texture: &texture,
-        //             mip_level: 0,
-        //             origin: wgpu::Origin3d::ZERO,
-        //         },
-        //         &rgba,
-        //         wgpu::ImageDataLayout {
-        //             offset: 0,
-        //             // bytes_per_row: Some(4 * dimensions.0),
-        //             // rows_per_image: Some(dimensions.1),
-        //             bytes_per_row: Some(4 * dimensions.0),
-        //             rows_per_image: Some(dimensions.1),
-        //         },
-        //         texture_extent,
-        //     );
-
-        //     let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        //     let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        //         address_mode_u: wgpu::AddressMode::ClampToEdge,
-        //         address_mode_v: wgpu::AddressMode::ClampToEdge,
-        //         address_mode_w: wgpu::AddressMode::ClampToEdge,
-        //         mag_filter: wgpu::FilterMode::Linear,
-        //         min_filter: wgpu::FilterMode::Nearest,
-        //         mipmap_filter: wgpu::FilterMode::Nearest,
-        //         ..Default::default()
-        //     });
-
-        //     Texture {
-        //         texture,
-        //         texture_view,
-        //         texture_sampler,
-        //         texture_extent,
-        //     }
-        // };
-
-        let color_uniform_size = std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
-        let transform_uniform_size = std::mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress;
-
-        let bind_group_layout =
-            app_context
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: true,
-                                min_binding_size: wgpu::BufferSize::new(color_uniform_size),
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                            ty: wgpu::BindingType::Buffer {
-                                ty: wgpu::BufferBindingType::Uniform,
-                                has_dynamic_offset: true,
-                                min_binding_size: wgpu::BufferSize::new(transform_uniform_size),
-                            },
-                            count: None,
-                        },
-                    ],
-                    label: Some("texture_bind_group_layout"),
-                });
+        let instance_capacity = Self::INITIAL_INSTANCE_CAPACITY;
+        let instance_buffer = Self::create_instance_buffer(&app_context.device, instance_capacity);
 
         let texture_bind_group_layout =
             app_context
@@ -855,119 +851,39 @@ impl QuadPipeline {
                     label: Some("texture_bind_group_layout"),
                 });
 
-        // Make the `uniform_alignment` >= `entity_uniform_size` and aligned to `min_uniform_buffer_offset_alignment`.
-        let transform_uniform_alignment = {
-            let alignment = app_context
-                .device
-                .limits()
-                .min_uniform_buffer_offset_alignment
-                as wgpu::BufferAddress;
-            align_to(transform_uniform_size, alignment)
-        };
-
-        let color_uniform_size = std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
-        // Make the `uniform_alignment` >= `entity_uniform_size` and aligned to `min_uniform_buffer_offset_alignment`.
-        let color_uniform_alignment = {
-            let alignment = app_context
-                .device
-                .limits()
-                .min_uniform_buffer_offset_alignment
-                as wgpu::BufferAddress;
-            align_to(color_uniform_size, alignment)
-        };
-
-        let model_mat4_buffer = app_context.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("translation Buffer"),
-            size: 40 * transform_uniform_alignment,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        ////////////////////////////////////////
-        /////////////// QUAD ///////////////////
-        //////// DEFAULT COLOR VALUE ///////////
-        ////////////////////////////////////////
-
-        // color_uniform_alignment = 256. Its too high, thats why I thought both ways would workd
-        // and it seemed they are. But this is just allocating space in the buffer for future use.
-        // with default color value
-        // for 40 colors actually
-        let color_slice: [f32; 400] = [1.0; 400];
-        let color_buffer = app_context
-            .device
-            .create_buffer_init(&BufferInitDescriptor {
-                label: Some("Line Color Buffer"),
-                contents: bytemuck::cast_slice(&color_slice),
-                // size: 40 * color_uniform_alignment,
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                // mapped_at_creation: false,
-            });
-
-        // with no default color value
-        //
-        // let color_slice: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
-        // let color_buffer = app_context.device.create_buffer(&wgpu::BufferDescriptor {
-        //     label: Some("Line Color Buffer"),
-        //     size: 2 * color_uniform_alignment,
-        //     usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        //     mapped_at_creation: false,
-        // });
-
-        ////////////////////////////////////////
-        /////////////// QUAD ///////////////////
-        //////// DEFAULT COLOR VALUE ///////////
-        ////////////////////////////////////////
-        let bind_group = app_context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("bind_group"),
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &color_buffer,
-                            offset: 0,
-                            size: wgpu::BufferSize::new(color_uniform_size),
-                        }),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
-                            buffer: &model_mat4_buffer,
-                            offset: 0,
-                            size: wgpu::BufferSize::new(transform_uniform_size),
-                        }),
-                    },
-                ],
-            });
-
-        let texture_bind_group = app_context
-            .device
-            .create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("texture_bind_group"),
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&diffuse_texture.texture_view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&diffuse_texture.texture_sampler),
-                    },
-                ],
-            });
-
         let module = wgpu::ShaderModuleDescriptor {
             label: Some("Builtin Quad Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("shaders/builtin_quad.wgsl").into()),
         };
 
-        let render_pipeline = RenderPipelineBuilder::new()
+        let bindless = BindlessTextures::new(&app_context.device);
+        match &bindless {
+            Some(bindless) => log::info!(
+                "Drawing quads with bindless textures, {} per draw call",
+                bindless.capacity
+            ),
+            None => log::info!("Bindless textures not supported, drawing quads per texture"),
+        }
+        let bind_group_layout = bindless
+            .as_ref()
+            .map_or(&texture_bind_group_layout, |bindless| {
+                &bindless.bind_group_layout
+            });
+
+        let mut builder = RenderPipelineBuilder::new();
+        if bindless.is_some() {
+            builder = builder.fragment_shader(wgpu::ShaderModuleDescriptor {
+                label: Some("Builtin Quad Bindless Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    include_str!("shaders/builtin_quad_bindless.wgsl").into(),
+                ),
+            });
+        }
+        let render_pipeline = builder
             .add_vertex_buffer_layout::<Vertex>()
+            .add_vertex_buffer_layout::<QuadInstance>()
             .add_color_target_state(wgpu::ColorTargetState {
-                format: app_context.config.format,
+                format: app_context.render_texture_format,
                 blend: Some(wgpu::BlendState {
                     color: wgpu::BlendComponent {
                         src_factor: wgpu::BlendFactor::SrcAlpha,
@@ -980,11 +896,7 @@ impl QuadPipeline {
             })
             .shader(module)
             // .with_wireframe(true)
-            .pipeline_layout_descriptor(
-                "Vertex layout descriptor",
-                &[&texture_bind_group_layout, &bind_group_layout],
-                &[],
-            )
+            .pipeline_layout_descriptor("Vertex layout descriptor", &[bind_group_layout], &[])
             .build(
                 &app_context.device,
                 "Quad - Render Pipeline",
@@ -994,22 +906,40 @@ impl QuadPipeline {
 
         Self {
             quad_info: vec![],
+            batches: vec![],
             render_pipeline,
             vertex_buffer,
 
-            color_buffer,
-            model_mat4_buffer,
-
-            color_uniform_alignment,
-            transform_uniform_alignment,
-
-            bind_group,
+            instance_buffer,
+            instance_capacity,
 
             // textures bindgroup layout
             texture_bind_group_layout,
+            bindless,
         }
     }
 
+    fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Quad - Instance Buffer"),
+            size: (capacity * std::mem::size_of::<QuadInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn write_instances(&mut self, app_context: &AppContext, instances: &[QuadInstance]) {
+        if instances.len() > self.instance_capacity {
+            self.instance_capacity = instances.len().next_power_of_two();
+            self.instance_buffer =
+                Self::create_instance_buffer(&app_context.device, self.instance_capacity);
+        }
+
+        app_context
+            .queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(instances));
+    }
+
     // What the actual fuck just happened?
     // pub fn draw<'r>(&'r self, color: [f32; 4], render_pass: &'r mut wgpu::RenderPass<'r>) {
     // pub fn draw<'a, 'r: 'a>(&'r self, color: [f32; 4], mut render_pass: wgpu::RenderPass<'a>) {
@@ -1021,14 +951,13 @@ impl QuadPipeline {
 }
 // QUAD
 
-struct BindableTexture {
+pub struct BindableTexture {
     texture: Texture,
     bind_group: wgpu::BindGroup,
+    options: TextureOptions,
 }
 
 use std::time::{Duration, Instant};
-use winit::monitor::{MonitorHandle, VideoMode};
-use winit::window::Fullscreen;
 
 struct Clock {
     previous_frame_instant: Instant,
@@ -1058,291 +987,507 @@ impl std::fmt::Debug for Clock {
 
 pub struct Engine {
     app_context: Arc<AppContext>,
-    texture_map: Arc<Mutex<HashMap<String, BindableTexture>>>,
+    textures: TextureStore,
+    white_texture: TextureHandle,
+    placeholder_texture: TextureHandle,
     quad_pipeline: QuadPipeline,
     line_pipeline: LinePipeline,
     circle_pipeline: CirclePipeline,
+    mipmap_generator: MipmapGenerator,
+    // `None` unless texture hot reloading is on
+    texture_watcher: Option<TextureWatcher>,
+    // started by the first `load_texture_async`
+    texture_loader: Option<TextureLoader>,
+    // `SharedTexture`s send their handle here when the last clone is dropped
+    texture_release_sender: Sender<TextureHandle>,
+    texture_release_receiver: Receiver<TextureHandle>,
+    camera: Camera,
+    // Handles that were drawn without pointing at a texture (e.g. from another `Engine`),
+    // so the warning is only logged once.
+    missing_textures: HashSet<TextureHandle>,
+    exit_requested: bool,
 }
 
 impl Engine {
-    pub fn new(
-        app_context: Arc<AppContext>,
-        texture_map: Arc<Mutex<HashMap<String, BindableTexture>>>,
-    ) -> Self {
+    pub fn new(app_context: Arc<AppContext>) -> Self {
         let quad_pipeline = QuadPipeline::new(app_context.clone());
         let line_pipeline = LinePipeline::new(app_context.clone());
         let circle_pipeline = CirclePipeline::new(app_context.clone());
 
-        Self {
+        let size = app_context.size();
+        let camera = Camera::new(size.width as f32, size.height as f32);
+
+        let (texture_release_sender, texture_release_receiver) = std::sync::mpsc::channel();
+
+        let mut engine = Self {
             app_context: app_context.clone(),
-            texture_map,
+            textures: TextureStore::default(),
+            // set right below, creating textures needs the quad pipeline
+            white_texture: TextureHandle::INVALID,
+            placeholder_texture: TextureHandle::INVALID,
             quad_pipeline,
             line_pipeline,
             circle_pipeline,
-        }
-    }
+            mipmap_generator: MipmapGenerator::new(&app_context.device),
+            texture_watcher: None,
+            texture_loader: None,
+            texture_release_sender,
+            texture_release_receiver,
+            camera,
+            missing_textures: HashSet::new(),
+            exit_requested: false,
+        };
 
-    pub fn create_dummy_texture_u32(&self, id: String, data: &[u8]) {
-        let mut texture_map = self.texture_map.lock().unwrap();
+        // the built-in textures' data always matches their size
+        engine.white_texture = engine
+            .create_dummy_texture(String::from(WHITE_TEXTURE_ID), &[0xFF, 0xFF, 0xFF, 0xFF])
+            .unwrap();
+        engine
+            .create_dummy_texture(String::from("1px-black"), &[0x00, 0x00, 0x00, 0xFF])
+            .unwrap();
+        // engine.create_dummy_texture(String::from("1px-black"), bytemuck::cast_slice(&[0xff000000 as u32]));
+        engine
+            .create_dummy_texture(String::from("1px-grey"), &[0xAA, 0xAA, 0xAA, 0xFF])
+            .unwrap();
 
-        texture_map.entry(id.clone()).or_insert_with(|| {
-            let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        // let mut pixels = vec![[0; 4]; 16 * 16];
+        let mut pixels: Vec<u32> = vec![0; 16 * 16];
+        for x in 0..16 {
+            for y in 0..16 {
+                if (x % 2) ^ (y % 2) == 1 {
+                    // pixels[y * 16 + x] = magenta_bytes;
+                    pixels[y * 16 + x] = 0xffff00ff;
+                } else {
+                    // pixels[y * 16 + x] = black_bytes;
+                    pixels[y * 16 + x] = 0xff000000;
+                }
+            }
+        }
 
-            let pixel_size = match format.block_dimensions() {
-                (1, 1) => format.block_copy_size(None).unwrap() as usize,
-                _ => panic!("Using pixel_size for compressed textures is invalid"),
-            };
+        // engine.create_magenta_texture(String::from("1px-magenta"), bytemuck::cast_slice(&pixels));
+        engine.placeholder_texture = engine
+            .create_dummy_texture_u32(String::from(PLACEHOLDER_TEXTURE_ID), 16, 16, &pixels)
+            .unwrap();
 
-            let texture_extent = wgpu::Extent3d {
-                width: 16,
-                height: 16,
-                depth_or_array_layers: 1,
-            };
+        engine
+    }
 
-            let texture = self
-                .app_context
-                .device
-                .create_texture(&wgpu::TextureDescriptor {
-                    label: None,
-                    size: texture_extent,
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format,
-                    view_formats: &[],
-                    // view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                });
+    /// Exits the event loop once the current event is handled. `Application::on_shutdown` is called before exiting.
+    pub fn request_exit(&mut self) {
+        self.exit_requested = true;
+    }
 
-            // dimensions are 256 x 256 for `happy-tree.png`
-            self.app_context.queue.write_texture(
-                // equivanet to wgpu::ImageCopyTexture... etc
-                // texture.as_image_copy()
-                wgpu::ImageCopyTexture {
-                    aspect: wgpu::TextureAspect::All,
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                },
-                data,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    // bytes_per_row: Some(4 * dimensions.0),
-                    bytes_per_row: Some(texture_extent.width * pixel_size as u32),
-                    rows_per_image: None,
-                    // rows_per_image: Some(dimensions.1),
-                },
-                texture_extent,
-            );
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
 
-            let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-            let texture_sampler =
-                self.app_context
-                    .device
-                    .create_sampler(&wgpu::SamplerDescriptor {
-                        address_mode_u: wgpu::AddressMode::ClampToEdge,
-                        address_mode_v: wgpu::AddressMode::ClampToEdge,
-                        address_mode_w: wgpu::AddressMode::ClampToEdge,
-                        mag_filter: wgpu::FilterMode::Linear,
-                        min_filter: wgpu::FilterMode::Nearest,
-                        mipmap_filter: wgpu::FilterMode::Nearest,
-                        ..Default::default()
-                    });
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
 
-            let texture = Texture {
-                texture,
-                texture_view,
-                texture_sampler,
-                texture_extent,
-            };
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
 
-            let bind_group =
-                self.app_context
-                    .device
-                    .create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("texture bindgroup"),
-                        layout: &self.quad_pipeline.texture_bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(&texture.texture_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::Sampler(&texture.texture_sampler),
-                            },
-                        ],
-                    });
+    /// Resizes the surface and fits the camera to the new logical size of the window.
+    /// Returns `false` when the window has no area (minimized) and nothing should be rendered.
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>, scale_factor: f64) -> bool {
+        if !self.app_context.resize(new_size) {
+            return false;
+        }
 
-            BindableTexture {
-                texture,
-                bind_group,
-            }
-        });
+        let logical_size = new_size.to_logical::<f32>(scale_factor);
+        self.camera.resize(logical_size.width, logical_size.height);
+        true
     }
 
-    pub fn create_dummy_texture(&self, id: String, data: &[u8]) {
-        let mut texture_map = self.texture_map.lock().unwrap();
-        texture_map.entry(id.clone()).or_insert_with(|| {
-            let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    /// A 1x1 texture of a single RGBA8 pixel.
+    pub fn create_dummy_texture(
+        &mut self,
+        id: String,
+        data: &[u8],
+    ) -> Result<TextureHandle, EngineError> {
+        self.create_rgba_texture(id, 1, 1, data)
+    }
 
-            let pixel_size = match format.block_dimensions() {
-                (1, 1) => format.block_copy_size(None).unwrap() as usize,
-                _ => panic!("Using pixel_size for compressed textures is invalid"),
-            };
+    /// A `width`x`height` texture from packed pixels, `0xAABBGGRR` (RGBA8 in little-endian order).
+    pub fn create_dummy_texture_u32(
+        &mut self,
+        id: String,
+        width: u32,
+        height: u32,
+        data: &[u32],
+    ) -> Result<TextureHandle, EngineError> {
+        self.create_rgba_texture(id, width, height, bytemuck::cast_slice(data))
+    }
 
-            // pixel_size = 4
+    /// A transparent `width`x`height` texture meant to be written to with `update_texture`,
+    /// e.g. for procedural content or a minimap drawn on the CPU.
+    pub fn create_dynamic_texture(
+        &mut self,
+        id: String,
+        width: u32,
+        height: u32,
+    ) -> Result<TextureHandle, EngineError> {
+        self.check_texture_size(width, height)?;
+        let data = vec![0; width as usize * height as usize * 4];
+        self.create_rgba_texture(id, width, height, &data)
+    }
 
-            // let data = vec![data; pixel_size];
+    /// Writes RGBA8 pixels, row by row, into `rect` of the texture. With mipmaps the smaller
+    /// levels are generated again.
+    /// Does nothing if `handle` doesn't point at a texture.
+    pub fn update_texture(
+        &mut self,
+        handle: TextureHandle,
+        rect: RegionRect,
+        rgba: &[u8],
+    ) -> Result<(), EngineError> {
+        let Some(bindable_texture) = self.textures.get(handle) else {
+            return Ok(());
+        };
+        let texture = &bindable_texture.texture;
+        let extent = texture.texture_extent;
+        let [x, y, width, height] = rect.to_pixel_rect(extent.width, extent.height);
+        if x.saturating_add(width) > extent.width || y.saturating_add(height) > extent.height {
+            return Err(EngineError::TextureUpdateOutOfBounds {
+                rect: [x, y, width, height],
+                width: extent.width,
+                height: extent.height,
+            });
+        }
+        let expected = width as usize * height as usize * 4;
+        if rgba.len() != expected {
+            return Err(EngineError::TextureDataSize {
+                expected,
+                actual: rgba.len(),
+            });
+        }
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
 
-            let texture_extent = wgpu::Extent3d {
-                width: 1,
-                height: 1,
+        self.app_context.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            rgba,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
                 depth_or_array_layers: 1,
-            };
+            },
+        );
+        if bindable_texture.options.mipmaps {
+            self.mipmap_generator.generate(
+                &self.app_context.device,
+                &self.app_context.queue,
+                &texture.texture,
+            );
+        }
+        Ok(())
+    }
 
-            let texture = self
-                .app_context
-                .device
-                .create_texture(&wgpu::TextureDescriptor {
-                    label: None,
-                    size: texture_extent,
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format,
-                    view_formats: &[],
-                    // view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-                });
+    /// wgpu can't create empty textures or ones bigger than the device allows.
+    fn check_texture_size(&self, width: u32, height: u32) -> Result<(), EngineError> {
+        let max = self.max_texture_dimension();
+        if width == 0 || height == 0 || width > max || height > max {
+            return Err(EngineError::InvalidTextureSize { width, height, max });
+        }
+        Ok(())
+    }
 
-            // dimensions are 256 x 256 for `happy-tree.png`
-            self.app_context.queue.write_texture(
-                // equivanet to wgpu::ImageCopyTexture... etc
-                // texture.as_image_copy()
-                wgpu::ImageCopyTexture {
-                    aspect: wgpu::TextureAspect::All,
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d::ZERO,
-                },
-                data,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    // bytes_per_row: Some(4 * dimensions.0),
-                    bytes_per_row: Some(texture_extent.width * pixel_size as u32),
-                    rows_per_image: None,
-                    // rows_per_image: Some(dimensions.1),
-                },
-                texture_extent,
-            );
+    fn create_rgba_texture(
+        &mut self,
+        id: String,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<TextureHandle, EngineError> {
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        // 4 bytes, the format isn't compressed
+        let pixel_size = format.block_copy_size(None).unwrap();
+
+        self.check_texture_size(width, height)?;
+        let expected = width as usize * height as usize * pixel_size as usize;
+        if data.len() != expected {
+            return Err(EngineError::TextureDataSize {
+                expected,
+                actual: data.len(),
+            });
+        }
 
-            let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-            let texture_sampler =
-                self.app_context
-                    .device
-                    .create_sampler(&wgpu::SamplerDescriptor {
-                        address_mode_u: wgpu::AddressMode::ClampToEdge,
-                        address_mode_v: wgpu::AddressMode::ClampToEdge,
-                        address_mode_w: wgpu::AddressMode::ClampToEdge,
-                        mag_filter: wgpu::FilterMode::Linear,
-                        min_filter: wgpu::FilterMode::Nearest,
-                        mipmap_filter: wgpu::FilterMode::Nearest,
-                        ..Default::default()
-                    });
+        let texture_extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
 
-            let texture = Texture {
+        let texture = self
+            .app_context
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some(&id),
+                size: texture_extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                view_formats: &[],
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            });
+
+        self.app_context.queue.write_texture(
+            // equivalent to texture.as_image_copy()
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * pixel_size),
+                rows_per_image: None,
+            },
+            texture_extent,
+        );
+
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let options = TextureOptions::default();
+        let texture_sampler = options
+            .effective_sampler()
+            .create_sampler(&self.app_context.device);
+
+        let bindable_texture = self.create_bindable_texture(
+            Texture {
                 texture,
                 texture_view,
                 texture_sampler,
                 texture_extent,
-            };
+            },
+            options,
+        );
+        Ok(self.textures.insert(id, bindable_texture, None))
+    }
 
-            let bind_group =
-                self.app_context
-                    .device
-                    .create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("texture bindgroup"),
-                        layout: &self.quad_pipeline.texture_bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(&texture.texture_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::Sampler(&texture.texture_sampler),
-                            },
-                        ],
-                    });
+    /// Loads the image at `texture_path` and registers it under `id`. A texture with that `id`
+    /// is replaced in place, so handles to it draw the new image.
+    pub fn create_texture(
+        &mut self,
+        id: String,
+        texture_path: impl AsRef<Path>,
+    ) -> Result<TextureHandle, EngineError> {
+        let texture_path = texture_path.as_ref();
+        let image = load_image(texture_path)?;
+        self.upload_image(
+            id,
+            &image,
+            TextureOptions::default(),
+            Some(texture_path.to_path_buf()),
+        )
+    }
 
-            BindableTexture {
-                texture,
-                bind_group,
-            }
-        });
+    /// Like `create_texture`, but with `options` instead of the defaults, e.g.
+    /// `SamplerOptions::pixel_art()` or `TextureOptions::default().with_mipmaps()`.
+    pub fn create_texture_with_options(
+        &mut self,
+        id: String,
+        texture_path: impl AsRef<Path>,
+        options: impl Into<TextureOptions>,
+    ) -> Result<TextureHandle, EngineError> {
+        let texture_path = texture_path.as_ref();
+        let image = load_image(texture_path)?;
+        self.upload_image(id, &image, options.into(), Some(texture_path.to_path_buf()))
     }
 
-    pub fn create_texture(&self, id: String, texture_path: &str) {
-        let mut texture_map = self.texture_map.lock().unwrap();
-        texture_map.entry(id.clone()).or_insert_with(|| {
-            let bytes = std::fs::read(texture_path).unwrap();
-            let texture = Texture::from_bytes(
+    /// Uploads an already decoded image and registers it under `id`. A texture with that `id`
+    /// is replaced in place, so handles to it draw the new image.
+    pub fn create_texture_from_image(
+        &mut self,
+        id: String,
+        image: &image::DynamicImage,
+    ) -> Result<TextureHandle, EngineError> {
+        self.create_texture_from_image_with_options(id, image, TextureOptions::default())
+    }
+
+    pub fn create_texture_from_image_with_options(
+        &mut self,
+        id: String,
+        image: &image::DynamicImage,
+        options: impl Into<TextureOptions>,
+    ) -> Result<TextureHandle, EngineError> {
+        self.upload_image(id, image, options.into(), None)
+    }
+
+    /// `source` is the file `image` was read from, kept for reloading.
+    fn upload_image(
+        &mut self,
+        id: String,
+        image: &image::DynamicImage,
+        options: TextureOptions,
+        source: Option<PathBuf>,
+    ) -> Result<TextureHandle, EngineError> {
+        self.check_texture_size(image.width(), image.height())?;
+        let texture = Texture::from_image(
+            &self.app_context.device,
+            &self.app_context.queue,
+            image,
+            Some(&id),
+            &options,
+        );
+        if options.mipmaps {
+            self.mipmap_generator.generate(
                 &self.app_context.device,
                 &self.app_context.queue,
-                &bytes,
-                &id,
-            )
-            .unwrap();
-
-            // This should be reutilizable for all quads that have a texture.
-            let bind_group_layout = self.app_context.device.create_bind_group_layout(
-                &wgpu::BindGroupLayoutDescriptor {
-                    label: Some("texture bindgroup layout"),
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                },
+                &texture.texture,
             );
+        }
+        let bindable_texture = self.create_bindable_texture(texture, options);
+        Ok(self.textures.insert(id, bindable_texture, source))
+    }
 
-            let bind_group =
-                self.app_context
-                    .device
-                    .create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: Some("texture bindgroup"),
-                        layout: &bind_group_layout,
-                        entries: &[
-                            wgpu::BindGroupEntry {
-                                binding: 0,
-                                resource: wgpu::BindingResource::TextureView(&texture.texture_view),
-                            },
-                            wgpu::BindGroupEntry {
-                                binding: 1,
-                                resource: wgpu::BindingResource::Sampler(&texture.texture_sampler),
-                            },
-                        ],
-                    });
+    /// Changes how an already loaded texture is sampled. Mipmaps can't be added afterwards,
+    /// textures created with them keep minifying trilinearly.
+    /// Does nothing if `handle` doesn't point at a texture.
+    pub fn set_texture_sampler(&mut self, handle: TextureHandle, sampler_options: SamplerOptions) {
+        let Some(bindable_texture) = self.textures.get(handle) else {
+            return;
+        };
+        let options = TextureOptions {
+            sampler: sampler_options,
+            ..bindable_texture.options
+        };
+        if bindable_texture.options == options {
+            return;
+        }
 
-            BindableTexture {
-                texture,
-                bind_group,
+        let texture_sampler = options
+            .effective_sampler()
+            .create_sampler(&self.app_context.device);
+        let bind_group = self
+            .create_texture_bind_group(&bindable_texture.texture.texture_view, &texture_sampler);
+
+        let bindable_texture = self.textures.get_mut(handle).unwrap();
+        bindable_texture.texture.texture_sampler = texture_sampler;
+        bindable_texture.bind_group = bind_group;
+        bindable_texture.options = options;
+    }
+
+    /// Options the texture was created with, `None` if `handle` doesn't point at a texture.
+    pub fn texture_options(&self, handle: TextureHandle) -> Option<TextureOptions> {
+        Some(self.textures.get(handle)?.options)
+    }
+
+    /// Handle of the texture registered under `id`.
+    pub fn texture_handle(&self, id: &str) -> Option<TextureHandle> {
+        self.textures.handle(id)
+    }
+
+    /// Id the texture was registered under.
+    pub fn texture_name(&self, handle: TextureHandle) -> Option<&str> {
+        self.textures.name(handle)
+    }
+
+    /// Drawn in place of textures that failed to load or don't exist.
+    pub fn placeholder_texture(&self) -> TextureHandle {
+        self.placeholder_texture
+    }
+
+    /// Frees the texture. Its handles stop pointing at anything and draw the placeholder.
+    /// Returns `false` if `handle` didn't point at a texture or is one of the built-in ones.
+    pub fn remove_texture(&mut self, handle: TextureHandle) -> bool {
+        if handle == self.white_texture || handle == self.placeholder_texture {
+            log::warn!("Built-in textures can't be removed");
+            return false;
+        }
+        if !self.textures.remove(handle) {
+            return false;
+        }
+
+        if let Some(watcher) = &mut self.texture_watcher {
+            watcher.forget(handle);
+        }
+        true
+    }
+
+    /// A reference-counted handle to the texture, which is removed once every clone is dropped.
+    /// Sharing the same texture again returns a clone of the live `SharedTexture`.
+    pub fn share_texture(&mut self, handle: TextureHandle) -> Option<SharedTexture> {
+        self.textures.share(handle, &self.texture_release_sender)
+    }
+
+    /// Number of textures alive and an estimate of the GPU memory they take.
+    pub fn texture_stats(&self) -> TextureStats {
+        self.textures.stats()
+    }
+
+    /// Removes the textures whose last `SharedTexture` was dropped. Called once per frame by the runner.
+    fn release_dropped_textures(&mut self) {
+        while let Ok(handle) = self.texture_release_receiver.try_recv() {
+            // shared again since the last clone was dropped
+            if !self.textures.is_shared(handle) {
+                self.remove_texture(handle);
             }
-        });
+        }
+    }
+
+    /// Largest width or height a texture can have on this device.
+    pub fn max_texture_dimension(&self) -> u32 {
+        self.app_context.device.limits().max_texture_dimension_2d
+    }
+
+    /// Width and height in pixels, `None` if `handle` doesn't point at a texture.
+    pub fn texture_dimensions(&self, handle: TextureHandle) -> Option<(u32, u32)> {
+        let extent = self.textures.get(handle)?.texture.texture_extent;
+        Some((extent.width, extent.height))
+    }
+
+    fn create_bindable_texture(
+        &self,
+        texture: Texture,
+        options: TextureOptions,
+    ) -> BindableTexture {
+        let bind_group =
+            self.create_texture_bind_group(&texture.texture_view, &texture.texture_sampler);
+
+        BindableTexture {
+            texture,
+            bind_group,
+            options,
+        }
+    }
+
+    fn create_texture_bind_group(
+        &self,
+        texture_view: &wgpu::TextureView,
+        texture_sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        self.app_context
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("texture bindgroup"),
+                layout: &self.quad_pipeline.texture_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(texture_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(texture_sampler),
+                    },
+                ],
+            })
     }
 
     pub fn begin_render<'rpass, 'a: 'rpass>(
@@ -1377,82 +1522,222 @@ impl Engine {
         scale: Vec3,
         angle: f32,
         color: [f32; 4],
-        texture_name: Option<String>,
+        texture: Option<TextureHandle>,
     ) {
-        self.quad_pipeline.quad_info.push(QuadInfo {
+        self.push_quad(position, scale, angle, color, texture, RegionRect::FULL);
+    }
+
+    /// Draws part of a texture, e.g. a sprite sheet frame or a `TextureAtlas` region.
+    /// Regions on the same texture are drawn together without changing bind groups.
+    pub fn render_region(
+        &mut self,
+        position: Vec3,
+        scale: Vec3,
+        angle: f32,
+        color: [f32; 4],
+        region: &TextureRegion,
+    ) {
+        self.push_quad(
+            position,
+            scale,
+            angle,
             color,
-            texture_name,
-            transform: TransformComponent {
-                position: Mat4::from_translation(position),
-                scale: Mat4::from_scale(scale),
-                rotation: Mat4::from_rotation_z(angle),
-            },
-        });
+            Some(region.texture),
+            region.rect,
+        );
     }
 
-    pub fn update_quad_data(&mut self, device: &wgpu::Device) {
-        for (i, quad) in self.quad_pipeline.quad_info.iter_mut().enumerate() {
-            let color_uniform_offset =
-                ((i + 1) * self.quad_pipeline.color_uniform_alignment as usize) as u32;
-            let transform_uniform_offset =
-                ((i + 1) * self.quad_pipeline.transform_uniform_alignment as usize) as u32;
+    /// Like `render_region`, but the region is repeated and offset inside the quad by
+    /// `uv_transform`, for scrolling backgrounds, conveyor belts or water. Whole textures tile
+    /// without seams with `SamplerOptions::with_repeat`.
+    pub fn render_repeated(
+        &mut self,
+        position: Vec3,
+        scale: Vec3,
+        angle: f32,
+        color: [f32; 4],
+        region: &TextureRegion,
+        uv_transform: UvTransform,
+    ) {
+        self.push_quad(
+            position,
+            scale,
+            angle,
+            color,
+            Some(region.texture),
+            region.rect,
+        )
+        .uv_transform = uv_transform;
+    }
 
-            self.app_context.queue.write_buffer(
-                &self.quad_pipeline.color_buffer,
-                color_uniform_offset as wgpu::BufferAddress,
-                bytemuck::cast_slice(&quad.color),
-            );
+    /// Draws a quad with everything `SpriteDraw` offers: flipping, a pivot, skew, corner colors
+    /// and repeating.
+    pub fn render_sprite(&mut self, sprite: &SpriteDraw) {
+        self.quad_pipeline.quad_info.push(QuadInfo {
+            model: sprite.model(),
+            corner_colors: sprite.corner_colors,
+            texture: sprite.region.map(|region| region.texture),
+            region: sprite.region.map_or(RegionRect::FULL, |region| region.rect),
+            uv_transform: sprite.uv_transform,
+            flip_x: sprite.flip_x,
+            flip_y: sprite.flip_y,
+        });
+    }
 
-            // not needed as we don't have a camera YET. To be used later.
-            // let view = Mat4::look_to_lh(vec3(0.0, 0.0, -1.0), vec3(0.0, 0.0, 0.0), Vec3::Y);
-            let _view = Mat4::IDENTITY;
+    fn push_quad(
+        &mut self,
+        position: Vec3,
+        scale: Vec3,
+        angle: f32,
+        color: [f32; 4],
+        texture: Option<TextureHandle>,
+        region: RegionRect,
+    ) -> &mut QuadInfo {
+        self.quad_pipeline.quad_info.push(QuadInfo {
+            model: Mat4::from_translation(position)
+                * Mat4::from_rotation_z(angle)
+                * Mat4::from_scale(scale),
+            corner_colors: [color; 4],
+            texture,
+            region,
+            uv_transform: UvTransform::IDENTITY,
+            flip_x: false,
+            flip_y: false,
+        });
+        self.quad_pipeline.quad_info.last_mut().unwrap()
+    }
 
-            // this is setting up the viewport basically
-            let proj = Mat4::orthographic_lh(0.0, 800.0, 0.0, 600.0, -1.0, 1.0);
-            let ar = 800.0 / 600.0;
+    pub fn update_quad_data(&mut self) {
+        // this is setting up the viewport basically
+        let proj = self.camera.view_projection();
+
+        let mut instances = Vec::with_capacity(self.quad_pipeline.quad_info.len());
+        // texture each quad is drawn with
+        let mut quad_textures = Vec::with_capacity(self.quad_pipeline.quad_info.len());
+        for quad in self.quad_pipeline.quad_info.iter() {
+            let texture = quad.texture.and_then(|handle| {
+                let texture = self.textures.get(handle);
+                if texture.is_none()
+                    && !self.textures.is_loading(handle)
+                    && self.missing_textures.insert(handle)
+                {
+                    log::warn!(
+                        "Texture {:?} not found, drawing the placeholder instead",
+                        handle
+                    );
+                }
+                texture
+            });
+            quad_textures.push(match quad.texture {
+                Some(handle) if texture.is_some() => handle,
+                Some(_) => self.placeholder_texture,
+                None => self.white_texture,
+            });
 
-            //let proj = Mat4::orthographic_lh(-ar, ar, -1.0, 1.0, -1.0, 1.0);
-            // let proj = Mat4::IDENTITY;
-            let model = quad.transform.position * quad.transform.rotation * quad.transform.scale;
+            let mut uv_rect = match (quad.region, texture) {
+                (RegionRect::Pixels { .. }, Some(texture)) => {
+                    let extent = texture.texture.texture_extent;
+                    quad.region.to_uv_rect(extent.width, extent.height)
+                }
+                // the placeholder is drawn whole
+                (RegionRect::Pixels { .. }, None) => [0.0, 0.0, 1.0, 1.0],
+                (RegionRect::Normalized(uv_rect), _) => uv_rect,
+            };
+            // sample the region from the opposite edge
+            if quad.flip_x {
+                uv_rect[0] += uv_rect[2];
+                uv_rect[2] = -uv_rect[2];
+            }
+            if quad.flip_y {
+                uv_rect[1] += uv_rect[3];
+                uv_rect[3] = -uv_rect[3];
+            }
 
-            let new_model = proj * model;
-            self.app_context.queue.write_buffer(
-                &self.quad_pipeline.model_mat4_buffer,
-                transform_uniform_offset as wgpu::BufferAddress,
-                bytemuck::cast_slice(&new_model.to_cols_array_2d()),
-            );
+            instances.push(QuadInstance {
+                model_mat4: (proj * quad.model).to_cols_array_2d(),
+                corner_colors: quad.corner_colors,
+                uv_rect,
+                texture_index: 0,
+                uv_transform: quad.uv_transform.to_array(),
+            });
         }
-    }
-
-    fn render_quads<'pass>(
-        &'pass self,
-        texture_map: &'pass TextureMap,
-        render_pass: &mut RenderPass<'pass>,
-    ) {
-        render_pass.set_pipeline(&self.quad_pipeline.render_pipeline);
 
-        for (i, quad) in self.quad_pipeline.quad_info.iter().enumerate() {
-            let bind_group: &wgpu::BindGroup;
+        self.quad_pipeline.batches.clear();
+        match &mut self.quad_pipeline.bindless {
+            Some(bindless) => {
+                // A batch ends when its texture array is full.
+                let batches = &mut self.quad_pipeline.batches;
+                let mut texture_sets: Vec<Vec<TextureHandle>> = vec![];
+                for (i, &texture) in quad_textures.iter().enumerate() {
+                    let fits = texture_sets
+                        .last()
+                        .is_some_and(|set| set.contains(&texture) || set.len() < bindless.capacity);
+                    if !fits {
+                        texture_sets.push(vec![]);
+                        batches.push(QuadBatch {
+                            instances: i as u32..i as u32,
+                            binding: QuadBatchBinding::Array(texture_sets.len() - 1),
+                        });
+                    }
+                    batches.last_mut().unwrap().instances.end += 1;
+
+                    let set = texture_sets.last_mut().unwrap();
+                    instances[i].texture_index = match set.iter().position(|&t| t == texture) {
+                        Some(index) => index as u32,
+                        None => {
+                            set.push(texture);
+                            set.len() as u32 - 1
+                        }
+                    };
+                }
 
-            if let Some(id) = &quad.texture_name {
-                bind_group = &texture_map.get(id).unwrap().bind_group;
-                render_pass.set_bind_group(0, bind_group, &[]);
-            } else {
-                bind_group = &texture_map.get("1px-white").unwrap().bind_group;
-                render_pass.set_bind_group(0, bind_group, &[]);
+                bindless.prepare(
+                    &self.app_context.device,
+                    &self.textures,
+                    texture_sets,
+                    self.white_texture,
+                );
+            }
+            None => {
+                // Quads are drawn in submission order, consecutive quads sharing a texture go in the same draw call.
+                let mut start = 0;
+                while start < quad_textures.len() {
+                    let texture = quad_textures[start];
+                    let mut end = start + 1;
+                    while end < quad_textures.len() && quad_textures[end] == texture {
+                        end += 1;
+                    }
+                    self.quad_pipeline.batches.push(QuadBatch {
+                        instances: start as u32..end as u32,
+                        binding: QuadBatchBinding::Texture(texture),
+                    });
+                    start = end;
+                }
             }
+        }
+
+        self.quad_pipeline
+            .write_instances(&self.app_context, &instances);
+    }
 
+    fn render_quads<'pass>(&'pass self, render_pass: &mut RenderPass<'pass>) {
+        render_pass.set_pipeline(&self.quad_pipeline.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.quad_pipeline.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.quad_pipeline.instance_buffer.slice(..));
+
+        for batch in &self.quad_pipeline.batches {
+            let bind_group = match batch.binding {
+                // resolved to an existing texture in `update_quad_data`
+                QuadBatchBinding::Texture(handle) => &self.textures.get(handle).unwrap().bind_group,
+                QuadBatchBinding::Array(index) => self
+                    .quad_pipeline
+                    .bindless
+                    .as_ref()
+                    .unwrap()
+                    .bind_group(index),
+            };
             render_pass.set_bind_group(0, bind_group, &[]);
-            render_pass.set_bind_group(
-                1,
-                &self.quad_pipeline.bind_group,
-                &[
-                    ((i + 1) * self.quad_pipeline.color_uniform_alignment as usize) as u32,
-                    ((i + 1) * self.quad_pipeline.transform_uniform_alignment as usize) as u32,
-                ],
-            );
-            render_pass.set_vertex_buffer(0, self.quad_pipeline.vertex_buffer.slice(..));
-            render_pass.draw(0..6, 0..1);
+            render_pass.draw(0..6, batch.instances.clone());
         }
     }
 
@@ -1466,7 +1751,7 @@ impl Engine {
         });
     }
 
-    pub fn update_line_data(&mut self, device: &wgpu::Device) {
+    pub fn update_line_data(&mut self) {
         for (i, line) in self.line_pipeline.line_info.iter_mut().enumerate() {
             let color_uniform_offset =
                 ((i + 1) * self.line_pipeline.color_uniform_alignment as usize) as u32;
@@ -1479,12 +1764,8 @@ impl Engine {
                 bytemuck::cast_slice(&line.color),
             );
 
-            // not needed as we don't have a camera YET. To be used later.
-            // let view = Mat4::look_to_lh(vec3(0.0, 0.0, -1.0), vec3(0.0, 0.0, 0.0), Vec3::Y);
-            let _view = Mat4::IDENTITY;
-
             // this is setting up the viewport basically
-            let proj = Mat4::orthographic_lh(0.0, 800.0, 0.0, 600.0, -1.0, 1.0);
+            let proj = self.camera.view_projection();
 
             let new_model = proj * line.transform.orig;
             self.app_context.queue.write_buffer(
@@ -1535,8 +1816,6 @@ impl Engine {
         pos
     }
     pub fn render_rect(&mut self, position: Vec3, size: Vec3, angle: f32, color: [f32; 4]) {
-        let fcx = position.x + size.x / 2.0;
-        let fcy = position.y - size.y / 2.0;
         //let p0 = Self::rotate_point(cx, cy, angle, position);
         //let p1 = Self::rotate_point(cx, cy, angle, vec3(p0.x + size.x, p0.y, p0.z));
         //let p2 = Self::rotate_point(cx, cy, angle, vec3(p1.x, p1.y - size.y, p0.z));
@@ -1623,8 +1902,7 @@ impl Engine {
                 bytemuck::cast_slice(&[circle.fade]),
             );
 
-            let _view = Mat4::IDENTITY;
-            let proj = Mat4::orthographic_lh(0.0, 800.0, 0.0, 600.0, -1.0, 1.0);
+            let proj = self.camera.view_projection();
             let model =
                 circle.transform.position * circle.transform.rotation * circle.transform.scale;
             let new_model = proj * model;
@@ -1662,67 +1940,42 @@ pub enum MyEvent {
         state: ElementState,
         physical_key: PhysicalKey,
     },
+    /// The window changed size or scale factor. `width` and `height` are physical pixels.
+    Resized {
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    },
+}
+
+pub async fn async_runner(app: impl Application + 'static) -> Result<(), EngineError> {
+    async_runner_with_config(app, EngineConfig::default()).await
 }
 
-pub type TextureMap = HashMap<String, BindableTexture>;
+pub async fn async_runner_with_config(
+    mut app: impl Application + 'static,
+    engine_config: EngineConfig,
+) -> Result<(), EngineError> {
+    let _ = env_logger::try_init();
 
-pub async fn async_runner(mut app: impl Application + 'static) {
-    let event_loop = EventLoop::new().unwrap();
-    let application_window_size2 = winit::dpi::LogicalSize::new(800.0, 600.0);
+    let event_loop = EventLoop::new()?;
     let main_window = Arc::new(
-        WindowBuilder::new()
-            .with_fullscreen(Some(Fullscreen::Borderless(None)))
-            .with_title("Game")
-            .build(&event_loop)
-            .unwrap(),
+        engine_config
+            .window_builder(&event_loop)
+            .build(&event_loop)?,
     );
 
     // IMPORTANT: this is different than before because I had added AppContext inside App along with Renderer
-    let app_context = Arc::new(AppContext::new(main_window.clone()).await.unwrap());
-
-    let texture_map: Arc<Mutex<TextureMap>> = Arc::new(Mutex::new(HashMap::new()));
-
-    let mut engine = Engine::new(app_context.clone(), texture_map.clone());
-    // engine.create_dummy_texture(String::from("1px-white"), &[0xFF, 0xFF, 0xFF, 0xFF]);
-    engine.create_dummy_texture(String::from("1px-black"), &[0x00, 0x00, 0x00, 0xFF]);
-    // engine.create_dummy_texture(String::from("1px-black"), bytemuck::cast_slice(&[0xff000000 as u32]));
-    engine.create_dummy_texture(String::from("1px-grey"), &[0xAA, 0xAA, 0xAA, 0xFF]);
-
-    // let x: &[u8] = bytemuck::cast_slice(&[0xff000000 as u32]);
-    // let hdp = &[0x00, 0x00, 0x00, 0xFF];
-    // println!("jajaj  {:?}", &x);
-    // println!("jaja hdp  {:?}", &hdp);
-    // panic!();
-
-    let magenta: u32 = 0xFF00FFFF;
-    // let magenta_ne = magenta.to_ne_bytes();
-    // println!("jaja hdp  {:?}", &magenta_ne);
-    // panic!();
-
-    let magenta_bytes = [0xFF, 0x00, 0xFF, 0xFF];
-    let black: u32 = 0x000000FF;
-    // let black_bytes = black.to_ne_bytes();
-    let black_bytes = [0x00, 0x00, 0x00, 0xFF];
-
-    // let mut pixels = vec![[0; 4]; 16 * 16];
-    let mut pixels: Vec<u32> = vec![0; 16 * 16];
-    for x in 0..16 {
-        for y in 0..16 {
-            if (x % 2) ^ (y % 2) == 1 {
-                // pixels[y * 16 + x] = magenta_bytes;
-                pixels[y * 16 + x] = 0xffff00ff;
-            } else {
-                // pixels[y * 16 + x] = black_bytes;
-                pixels[y * 16 + x] = 0xff000000;
-            }
-        }
-    }
+    let app_context = Arc::new(AppContext::new(main_window.clone(), &engine_config).await?);
 
-    // engine.create_magenta_texture(String::from("1px-magenta"), bytemuck::cast_slice(&pixels));
-    //println!("{:?}", &pixels);
-    let xd: &[u8] = bytemuck::cast_slice(&pixels);
-    //println!("{:?}", xd);
-    engine.create_dummy_texture_u32(String::from("1px-white"), bytemuck::cast_slice(&pixels));
+    let mut engine = Engine::new(app_context.clone());
+    let logical_size = main_window
+        .inner_size()
+        .to_logical::<f32>(main_window.scale_factor());
+    engine
+        .camera_mut()
+        .resize(logical_size.width, logical_size.height);
+    engine.set_texture_hot_reload(engine_config.texture_hot_reload);
 
     // engine.create_texture(id, texture_path)
     let mut framework = Framework::new(
@@ -1742,111 +1995,174 @@ pub async fn async_runner(mut app: impl Application + 'static) {
         fps: 0,
     };
 
-    let _ = event_loop.run(move |event, _event_loop| match event {
-        Event::WindowEvent {
-            window_id: _,
-            event,
-        } => {
-            framework.handle_event(&event);
-            match event {
-                WindowEvent::KeyboardInput {
-                    event:
-                        KeyEvent {
-                            physical_key: PhysicalKey::Code(KeyCode::KeyQ),
-                            ..
-                        },
-                    ..
-                } => {
-                    dbg!("Received closed event");
-                    panic!("Closed");
-                }
-                WindowEvent::KeyboardInput {
-                    event:
-                        KeyEvent {
-                            state,
-                            physical_key,
-                            ..
-                        },
-                    ..
-                } => {
-                    let new_event = MyEvent::KeyboardInput {
-                        state,
-                        physical_key,
-                    };
-
-                    dbg!(new_event);
+    // Nothing is rendered while the window has no area, e.g. when it is minimized.
+    let mut minimized = false;
+    // Nothing is rendered while the app is in the background either.
+    let mut suspended = false;
+    let quit_key = engine_config.quit_key;
+
+    event_loop.run(move |event, event_loop| {
+        match event {
+            Event::WindowEvent {
+                window_id: _,
+                event,
+            } => {
+                framework.handle_event(&event);
+                match event {
+                    WindowEvent::CloseRequested => engine.request_exit(),
+
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                state: ElementState::Pressed,
+                                physical_key: PhysicalKey::Code(key_code),
+                                ..
+                            },
+                        ..
+                    } if Some(key_code) == quit_key => engine.request_exit(),
 
-                    app.on_event(&mut engine, new_event);
-                }
+                    WindowEvent::Focused(focused) => app.on_focus_changed(&mut engine, focused),
 
-                WindowEvent::RedrawRequested => {
-                    clock.tick();
-                    framework.prepare();
-                    app.on_update(&mut engine, clock.delta_time.as_secs_f32());
-                    println!("{:?}", clock);
-                  
-                    app.on_render(&mut engine);
-                    // IMPORTANT:
-                    // I can't store a renderpass because it needs a reference to a view and the view will
-                    // change upon resizing
-                    let frame = app_context
-                        .surface
-                        .get_current_texture()
-                        .or_else(|_| {
-                            app_context.reconfigure_surface();
-                            app_context.surface.get_current_texture()
-                        })
-                        .unwrap();
-
-                    let view = frame
-                        .texture
-                        .create_view(&wgpu::TextureViewDescriptor::default());
-
-                    let mut encoder = app_context.device.create_command_encoder(
-                        &wgpu::CommandEncoderDescriptor {
-                            label: Some("pixels_command_encoder"),
-                        },
-                    );
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                state,
+                                physical_key,
+                                ..
+                            },
+                        ..
+                    } => {
+                        let new_event = MyEvent::KeyboardInput {
+                            state,
+                            physical_key,
+                        };
 
-                    /////////////////////////////////////////////////
-                    ////////////////// RENDERING ////////////////////
-                    /////////////////////////////////////////////////
-
-                    engine.update_quad_data(&app_context.device);
-                    engine.update_line_data(&app_context.device);
-                    engine.update_circle_data();
-                    let texture_map = texture_map.lock().unwrap();
-                    {
-                        let mut rpass = engine.begin_render(&mut encoder, &view);
-                        engine.render_quads(&texture_map, &mut rpass);
-                        engine.render_lines(&mut rpass);
-                        engine.render_circles(&mut rpass);
+                        app.on_event(&mut engine, new_event);
                     }
 
-                    {
-                        framework.render(&mut encoder, &view, &app_context);
+                    WindowEvent::Resized(new_size) => {
+                        let scale_factor = main_window.scale_factor();
+                        minimized = !engine.resize(new_size, scale_factor);
+                        framework.resize(new_size.width, new_size.height);
+
+                        app.on_event(
+                            &mut engine,
+                            MyEvent::Resized {
+                                width: new_size.width,
+                                height: new_size.height,
+                                scale_factor,
+                            },
+                        );
+                        main_window.request_redraw();
                     }
-                    /////////////////////////////////////////////////
-                    ////////////////// RENDERING ////////////////////
-                    /////////////////////////////////////////////////
 
-                    engine.quad_pipeline.quad_info.clear();
-                    engine.line_pipeline.line_info.clear();
-                    engine.circle_pipeline.circle_info.clear();
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        // The physical size usually changes as well, in which case a `Resized` follows.
+                        // The logical size the camera uses changes regardless, so refit everything here.
+                        let new_size = main_window.inner_size();
+                        minimized = !engine.resize(new_size, scale_factor);
+                        framework.scale_factor(scale_factor);
+                        framework.resize(new_size.width, new_size.height);
+
+                        app.on_event(
+                            &mut engine,
+                            MyEvent::Resized {
+                                width: new_size.width,
+                                height: new_size.height,
+                                scale_factor,
+                            },
+                        );
+                    }
 
-                    app_context.queue.submit(Some(encoder.finish()));
-                    frame.present();
+                    WindowEvent::RedrawRequested if minimized || suspended => (),
+
+                    WindowEvent::RedrawRequested => {
+                        clock.tick();
+                        engine.release_dropped_textures();
+                        engine.poll_texture_changes();
+                        engine.poll_texture_loads();
+                        framework.prepare();
+                        app.on_update(&mut engine, clock.delta_time.as_secs_f32());
+
+                        app.on_render(&mut engine);
+                        // IMPORTANT:
+                        // I can't store a renderpass because it needs a reference to a view and the view will
+                        // change upon resizing
+                        let frame = app_context
+                            .surface
+                            .get_current_texture()
+                            .or_else(|_| {
+                                app_context.reconfigure_surface();
+                                app_context.surface.get_current_texture()
+                            })
+                            .unwrap();
+
+                        let view = frame
+                            .texture
+                            .create_view(&wgpu::TextureViewDescriptor::default());
+
+                        let mut encoder = app_context.device.create_command_encoder(
+                            &wgpu::CommandEncoderDescriptor {
+                                label: Some("pixels_command_encoder"),
+                            },
+                        );
+
+                        /////////////////////////////////////////////////
+                        ////////////////// RENDERING ////////////////////
+                        /////////////////////////////////////////////////
+
+                        engine.update_quad_data();
+                        engine.update_line_data();
+                        engine.update_circle_data();
+                        {
+                            let mut rpass = engine.begin_render(&mut encoder, &view);
+                            engine.render_quads(&mut rpass);
+                            engine.render_lines(&mut rpass);
+                            engine.render_circles(&mut rpass);
+                        }
+
+                        {
+                            framework.render(&mut encoder, &view, &app_context);
+                        }
+                        /////////////////////////////////////////////////
+                        ////////////////// RENDERING ////////////////////
+                        /////////////////////////////////////////////////
+
+                        engine.quad_pipeline.quad_info.clear();
+                        engine.line_pipeline.line_info.clear();
+                        engine.circle_pipeline.circle_info.clear();
+
+                        app_context.queue.submit(Some(encoder.finish()));
+                        frame.present();
+
+                        main_window.request_redraw();
+                    }
 
-                    main_window.request_redraw();
+                    _ => (),
                 }
-
-                _ => (),
             }
+            Event::Suspended => {
+                suspended = true;
+                app.on_suspend(&mut engine);
+            }
+            // Also sent once at startup, which isn't a resume from the app's point of view.
+            Event::Resumed if suspended => {
+                suspended = false;
+                app.on_resume(&mut engine);
+                main_window.request_redraw();
+            }
+            Event::LoopExiting => app.on_shutdown(&mut engine),
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: _ },
+                ..
+            } => (),
+            _ => (),
         }
-        Event::DeviceEvent {
-            event: DeviceEvent::MouseMotion { delta: _ },
-            ..
-        } => (),
-        _ => (),
-    });
+
+        if engine.exit_requested() {
+            event_loop.exit();
+        }
+    })?;
+
+    Ok(())
 }
@@ -0,0 +1,190 @@
+use glam::{BVec2, Vec2, Vec3};
+
+use crate::{Engine, RegionRect, TextureHandle, TextureRegion};
+
+/// Tiny tiles over a big area would mean thousands of quads, past this they grow instead.
+const MAX_TILES_PER_AXIS: f32 = 256.0;
+
+/// Border widths in pixels of the source texture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BorderInsets {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl BorderInsets {
+    pub fn new(left: u32, right: u32, top: u32, bottom: u32) -> Self {
+        Self {
+            left,
+            right,
+            top,
+            bottom,
+        }
+    }
+
+    /// The same width on every side.
+    pub fn uniform(width: u32) -> Self {
+        Self::new(width, width, width, width)
+    }
+}
+
+/// How the edges and the center fill the space between the corners.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NineSliceMode {
+    #[default]
+    Stretch,
+    /// Repeated at native size, the last tile is cut off.
+    Tile,
+}
+
+/// A texture region split into corners, edges and center by `insets`, drawn at any size with
+/// `Engine::render_nine_slice`. The corners keep their size, the rest fills the gaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NineSlice {
+    pub region: TextureRegion,
+    pub insets: BorderInsets,
+    pub mode: NineSliceMode,
+}
+
+impl NineSlice {
+    pub fn new(region: TextureRegion, insets: BorderInsets) -> Self {
+        Self {
+            region,
+            insets,
+            mode: NineSliceMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: NineSliceMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+/// Splits `length` into start, middle and end. The borders shrink evenly when they don't fit.
+fn split(length: f32, start: u32, end: u32) -> [f32; 3] {
+    let borders = (start + end) as f32;
+    let border_scale = if borders > length && borders > 0.0 {
+        length / borders
+    } else {
+        1.0
+    };
+    let start = start as f32 * border_scale;
+    let end = end as f32 * border_scale;
+    [start, length - start - end, end]
+}
+
+impl Engine {
+    /// Draws `nine_slice` centered at `position` with a total `size`, for dialog boxes, buttons
+    /// and other scalable frames. Textures that aren't loaded are drawn as one quad.
+    pub fn render_nine_slice(
+        &mut self,
+        nine_slice: &NineSlice,
+        position: Vec3,
+        size: Vec2,
+        color: [f32; 4],
+    ) {
+        let texture = nine_slice.region.texture;
+        let Some((texture_width, texture_height)) = self.texture_dimensions(texture) else {
+            self.render_region(position, size.extend(1.0), 0.0, color, &nine_slice.region);
+            return;
+        };
+
+        let [x, y, width, height] = nine_slice
+            .region
+            .rect
+            .to_pixel_rect(texture_width, texture_height);
+        let insets = nine_slice.insets;
+        let left = insets.left.min(width);
+        let right = insets.right.min(width - left);
+        let top = insets.top.min(height);
+        let bottom = insets.bottom.min(height - top);
+
+        // source columns and rows in pixels, `(start, length)`, top to bottom
+        let source_columns = [
+            (x, left),
+            (x + left, width - left - right),
+            (x + width - right, right),
+        ];
+        let source_rows = [
+            (y, top),
+            (y + top, height - top - bottom),
+            (y + height - bottom, bottom),
+        ];
+        let columns = split(size.x, left, right);
+        let rows = split(size.y, top, bottom);
+
+        let texture_size = Vec2::new(texture_width as f32, texture_height as f32);
+        let mut piece_top = position.y + size.y / 2.0;
+        for (row, (source_y, source_height)) in source_rows.into_iter().enumerate() {
+            let mut piece_left = position.x - size.x / 2.0;
+            for (column, (source_x, source_width)) in source_columns.into_iter().enumerate() {
+                let piece_size = Vec2::new(columns[column], rows[row]);
+                let source_size = Vec2::new(source_width as f32, source_height as f32);
+                let tile_size = match nine_slice.mode {
+                    NineSliceMode::Stretch => piece_size,
+                    // corners are always scaled, edges only tile along their length
+                    NineSliceMode::Tile => {
+                        Vec2::select(BVec2::new(column == 1, row == 1), source_size, piece_size)
+                    }
+                };
+                if piece_size.min_element() > 0.0 && source_size.min_element() > 0.0 {
+                    let uv_origin = Vec2::new(source_x as f32, source_y as f32) / texture_size;
+                    self.push_tiles(
+                        Vec3::new(piece_left, piece_top, position.z),
+                        piece_size,
+                        tile_size.max(piece_size / MAX_TILES_PER_AXIS),
+                        [uv_origin, source_size / texture_size],
+                        color,
+                        texture,
+                    );
+                }
+                piece_left += piece_size.x;
+            }
+            piece_top -= rows[row];
+        }
+    }
+
+    /// Fills `size` below and right of `top_left` with tiles of `tile_size` showing
+    /// `[uv_origin, uv_size]`, cutting off the last row and column.
+    fn push_tiles(
+        &mut self,
+        top_left: Vec3,
+        size: Vec2,
+        tile_size: Vec2,
+        [uv_origin, uv_size]: [Vec2; 2],
+        color: [f32; 4],
+        texture: TextureHandle,
+    ) {
+        let mut tile_top = 0.0;
+        while tile_top < size.y {
+            let mut tile_left = 0.0;
+            while tile_left < size.x {
+                let visible_size = tile_size.min(size - Vec2::new(tile_left, tile_top));
+                let visible_uv_size = uv_size * visible_size / tile_size;
+                let center = Vec3::new(
+                    top_left.x + tile_left + visible_size.x / 2.0,
+                    top_left.y - tile_top - visible_size.y / 2.0,
+                    top_left.z,
+                );
+                self.push_quad(
+                    center,
+                    visible_size.extend(1.0),
+                    0.0,
+                    color,
+                    Some(texture),
+                    RegionRect::Normalized([
+                        uv_origin.x,
+                        uv_origin.y,
+                        visible_uv_size.x,
+                        visible_uv_size.y,
+                    ]),
+                );
+                tile_left += tile_size.x;
+            }
+            tile_top += tile_size.y;
+        }
+    }
+}
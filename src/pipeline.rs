@@ -5,6 +5,8 @@ pub trait VertexDescriptor<'a> {
 
 pub struct RenderPipelineBuilder<'a> {
     shader_module_descriptor: Option<wgpu::ShaderModuleDescriptor<'a>>,
+    // fragment stage from another module, the vertex one's by default
+    fragment_shader_module_descriptor: Option<wgpu::ShaderModuleDescriptor<'a>>,
     pipeline_layout_descriptor: Option<wgpu::PipelineLayoutDescriptor<'a>>,
     render_pipeline_descriptor: Option<wgpu::RenderPipelineDescriptor<'a>>,
     wireframe: bool,
@@ -17,6 +19,7 @@ impl<'a> RenderPipelineBuilder<'a> {
     pub fn new() -> Self {
         Self {
             shader_module_descriptor: None,
+            fragment_shader_module_descriptor: None,
             pipeline_layout_descriptor: None,
             render_pipeline_descriptor: None,
             wireframe: false,
@@ -38,6 +41,15 @@ impl<'a> RenderPipelineBuilder<'a> {
         self
     }
 
+    /// Takes the fragment entry point from this module instead of the one passed to `shader`.
+    pub fn fragment_shader(
+        mut self,
+        shader_module_descriptor: wgpu::ShaderModuleDescriptor<'a>,
+    ) -> Self {
+        self.fragment_shader_module_descriptor = Some(shader_module_descriptor);
+        self
+    }
+
     pub fn pipeline_layout_descriptor(
         mut self,
         label: &'a str,
@@ -93,6 +105,9 @@ impl<'a> RenderPipelineBuilder<'a> {
         };
 
         let shader_module = device.create_shader_module(shader_module_descriptor);
+        let fragment_shader_module = self
+            .fragment_shader_module_descriptor
+            .map(|descriptor| device.create_shader_module(descriptor));
 
         let vertex_state = wgpu::VertexState {
             module: &shader_module,
@@ -101,7 +116,7 @@ impl<'a> RenderPipelineBuilder<'a> {
         };
 
         let fragment_state = wgpu::FragmentState {
-            module: &shader_module,
+            module: fragment_shader_module.as_ref().unwrap_or(&shader_module),
             entry_point: fs_entry_point,
             targets: &self.color_target_states,
         };
@@ -112,6 +127,21 @@ impl<'a> RenderPipelineBuilder<'a> {
 
         let pipeline_layout = device.create_pipeline_layout(&pipeline_layout_descriptor);
 
+        // Wireframe is a debugging aid, so don't fail on adapters without line polygon mode.
+        let wireframe = if self.wireframe
+            && !device
+                .features()
+                .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            log::warn!(
+                "{}: POLYGON_MODE_LINE is not supported, ignoring wireframe",
+                pipeline_label
+            );
+            false
+        } else {
+            self.wireframe
+        };
+
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some(pipeline_label),
             layout: Some(&pipeline_layout),
@@ -124,7 +154,7 @@ impl<'a> RenderPipelineBuilder<'a> {
                 //clock
                 front_face: wgpu::FrontFace::Ccw,
                 cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: if self.wireframe {
+                polygon_mode: if wireframe {
                     wgpu::PolygonMode::Line
                 } else {
                     wgpu::PolygonMode::Fill
@@ -147,6 +177,7 @@ impl Clone for RenderPipelineBuilder<'_> {
     fn clone(&self) -> Self {
         Self {
             shader_module_descriptor: self.shader_module_descriptor.clone(),
+            fragment_shader_module_descriptor: self.fragment_shader_module_descriptor.clone(),
             pipeline_layout_descriptor: self.pipeline_layout_descriptor.clone(),
             render_pipeline_descriptor: self.render_pipeline_descriptor.clone(),
             wireframe: self.wireframe,
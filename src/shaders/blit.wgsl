@@ -0,0 +1,28 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle covering the whole target, no vertex buffer needed.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32(i32(vertex_index) / 2);
+    let y = f32(i32(vertex_index) & 1);
+    let tex_coords = vec2<f32>(x * 2.0, y * 2.0);
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(tex_coords.x * 2.0 - 1.0, 1.0 - tex_coords.y * 2.0, 0.0, 1.0);
+    out.tex_coords = tex_coords;
+    return out;
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+// Sampling halfway between texels with a linear filter averages 2x2 texels of the previous level.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(source, source_sampler, in.tex_coords);
+}
@@ -5,21 +5,60 @@ struct VertexInput {
     @location(1) tex_coords: vec2<f32>,
 };
 
+// One per quad
+struct InstanceInput {
+    @location(2) model_mat4_0: vec4<f32>,
+    @location(3) model_mat4_1: vec4<f32>,
+    @location(4) model_mat4_2: vec4<f32>,
+    @location(5) model_mat4_3: vec4<f32>,
+    // blended between the corners
+    @location(6) color_top_left: vec4<f32>,
+    @location(7) color_top_right: vec4<f32>,
+    @location(8) color_bottom_right: vec4<f32>,
+    @location(9) color_bottom_left: vec4<f32>,
+    // x, y, width, height of the region of the texture to sample, negative sizes flip it
+    @location(10) uv_rect: vec4<f32>,
+    // texture in the binding array, only read by `builtin_quad_bindless.wgsl`
+    @location(11) texture_index: u32,
+    // repeat x, y, offset x, y of the region inside the quad
+    @location(12) uv_transform: vec4<f32>,
+};
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     //@location(1) color: vec3<f32>,
     //@location(0) tex_coords: vec2<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) texture_index: u32,
+    @location(3) @interpolate(flat) uv_rect: vec4<f32>,
+    // 1 when the region repeats or scrolls inside the quad
+    @location(4) @interpolate(flat) wrap: u32,
 };
 
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    instance: InstanceInput,
 ) -> VertexOutput {
+    let model_mat4 = mat4x4<f32>(
+        instance.model_mat4_0,
+        instance.model_mat4_1,
+        instance.model_mat4_2,
+        instance.model_mat4_3,
+    );
+
     var out: VertexOutput;
-    //out.color = model.color;
-    out.tex_coords = model.tex_coords;
+    // the quad's own texture coordinates tell which corner this is, y goes down
+    let top = mix(instance.color_top_left, instance.color_top_right, model.tex_coords.x);
+    let bottom = mix(instance.color_bottom_left, instance.color_bottom_right, model.tex_coords.x);
+    out.color = mix(top, bottom, model.tex_coords.y);
+    out.texture_index = instance.texture_index;
+    // in region sizes, mapped into the region in the fragment stage
+    out.tex_coords = model.tex_coords * instance.uv_transform.xy + instance.uv_transform.zw;
+    out.uv_rect = instance.uv_rect;
+    out.wrap = select(0u, 1u, any(instance.uv_transform != vec4<f32>(1.0, 1.0, 0.0, 0.0)));
     out.clip_position = model_mat4 * vec4<f32>(model.position, 1.0);
     return out;
 }
@@ -30,21 +69,24 @@ var t_diffuse: texture_2d<f32>;
 // fragment
 @group(0) @binding(1)
 var s_diffuse: sampler;
-// fragment
-@group(1) @binding(0)
-// TODO: Document better
-// For some reason I need to tag color with 'uniform' as opposed to the fucking textures
-var<uniform> color: vec4<f32>;
 
-// vertex - fragment
-@group(1) @binding(1)
-var<uniform> model_mat4: mat4x4<f32>;
 
+// Texture coordinates inside the quad's region. Wrapping breaks the derivatives at the
+// region's edges, so they're taken from the unwrapped coordinates for `textureSampleGrad`.
+fn region_uv(in: VertexOutput) -> vec2<f32> {
+    var tex_coords = in.tex_coords;
+    if in.wrap != 0u {
+        tex_coords = fract(tex_coords);
+    }
+    return in.uv_rect.xy + tex_coords * in.uv_rect.zw;
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     //return textureSample(t_diffuse, s_diffuse, in.tex_coords) * vec4<f32>(in.color.xyz, 1.0);
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * color;
+    let ddx = dpdx(in.tex_coords) * in.uv_rect.zw;
+    let ddy = dpdy(in.tex_coords) * in.uv_rect.zw;
+    return textureSampleGrad(t_diffuse, s_diffuse, region_uv(in), ddx, ddy) * in.color;
 }
 
 // TODO: watch Cherno videos on Textures
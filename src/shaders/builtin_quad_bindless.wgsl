@@ -0,0 +1,35 @@
+// Fragment stage for `builtin_quad.wgsl` when the device supports binding arrays.
+// Every quad picks its texture by index, so quads with different textures share a draw call.
+
+// same as the one in `builtin_quad.wgsl`
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) texture_index: u32,
+    @location(3) @interpolate(flat) uv_rect: vec4<f32>,
+    @location(4) @interpolate(flat) wrap: u32,
+};
+
+// same as the one in `builtin_quad.wgsl`
+fn region_uv(in: VertexOutput) -> vec2<f32> {
+    var tex_coords = in.tex_coords;
+    if in.wrap != 0u {
+        tex_coords = fract(tex_coords);
+    }
+    return in.uv_rect.xy + tex_coords * in.uv_rect.zw;
+}
+
+@group(0) @binding(0)
+var t_diffuse: binding_array<texture_2d<f32>>;
+// one sampler per texture, each texture keeps its own `SamplerOptions`
+@group(0) @binding(1)
+var s_diffuse: binding_array<sampler>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let ddx = dpdx(in.tex_coords) * in.uv_rect.zw;
+    let ddy = dpdy(in.tex_coords) * in.uv_rect.zw;
+    let index = in.texture_index;
+    return textureSampleGrad(t_diffuse[index], s_diffuse[index], region_uv(in), ddx, ddy) * in.color;
+}
@@ -0,0 +1,402 @@
+use std::path::Path;
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::{Engine, EngineError, TextureHandle};
+
+/// Part of a texture to draw, see `Engine::render_region`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionRect {
+    /// In pixels, origin at the top-left corner of the texture.
+    Pixels {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    /// `[x, y, width, height]` in `0.0..=1.0`, origin at the top-left corner of the texture.
+    Normalized([f32; 4]),
+}
+
+impl RegionRect {
+    pub const FULL: RegionRect = RegionRect::Normalized([0.0, 0.0, 1.0, 1.0]);
+
+    /// `[x, y, width, height]` in texture coordinates for a texture of the given size.
+    pub fn to_uv_rect(self, texture_width: u32, texture_height: u32) -> [f32; 4] {
+        match self {
+            RegionRect::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let texture_width = texture_width.max(1) as f32;
+                let texture_height = texture_height.max(1) as f32;
+                [
+                    x as f32 / texture_width,
+                    y as f32 / texture_height,
+                    width as f32 / texture_width,
+                    height as f32 / texture_height,
+                ]
+            }
+            RegionRect::Normalized(uv_rect) => uv_rect,
+        }
+    }
+
+    /// `[x, y, width, height]` in pixels for a texture of the given size, normalized rects are
+    /// rounded to whole pixels.
+    pub fn to_pixel_rect(self, texture_width: u32, texture_height: u32) -> [u32; 4] {
+        match self {
+            RegionRect::Pixels {
+                x,
+                y,
+                width,
+                height,
+            } => [x, y, width, height],
+            RegionRect::Normalized([x, y, width, height]) => {
+                let to_pixels =
+                    |value: f32, size: u32| (value * size as f32).round().max(0.0) as u32;
+                [
+                    to_pixels(x, texture_width),
+                    to_pixels(y, texture_height),
+                    to_pixels(width, texture_width),
+                    to_pixels(height, texture_height),
+                ]
+            }
+        }
+    }
+}
+
+/// Everything `Engine::render_sprite` can do with a quad. `render_quad` and `render_region`
+/// are shortcuts for the common cases.
+///
+/// ```ignore
+/// let sprite = SpriteDraw::new(position, Vec2::new(64.0, 64.0))
+///     .with_region(frame)
+///     .with_flip(facing_left, false)
+///     .with_pivot(Vec2::new(0.5, 0.0));
+/// engine.render_sprite(&sprite);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteDraw {
+    pub position: Vec3,
+    pub size: Vec2,
+    /// Counter-clockwise, in radians, around the pivot.
+    pub angle: f32,
+    /// Point of the sprite placed at `position` and rotated around. `(0, 0)` is the bottom-left
+    /// corner, `(1, 1)` the top-right one, the center by default.
+    pub pivot: Vec2,
+    /// Mirrors the texture, e.g. for characters facing the other way.
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Shear angles in radians, `x` slants vertical edges and `y` horizontal ones.
+    pub skew: Vec2,
+    /// Top-left, top-right, bottom-right, bottom-left, blended across the sprite.
+    pub corner_colors: [[f32; 4]; 4],
+    /// `None` draws a plain colored quad.
+    pub region: Option<TextureRegion>,
+    pub uv_transform: UvTransform,
+}
+
+impl SpriteDraw {
+    pub fn new(position: Vec3, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            angle: 0.0,
+            pivot: Vec2::splat(0.5),
+            flip_x: false,
+            flip_y: false,
+            skew: Vec2::ZERO,
+            corner_colors: [[1.0; 4]; 4],
+            region: None,
+            uv_transform: UvTransform::IDENTITY,
+        }
+    }
+
+    pub fn with_region(mut self, region: TextureRegion) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// The whole texture.
+    pub fn with_texture(self, texture: TextureHandle) -> Self {
+        self.with_region(TextureRegion::whole(texture))
+    }
+
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn with_pivot(mut self, pivot: Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn with_skew(mut self, skew: Vec2) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    /// The same color on every corner.
+    pub fn with_color(self, color: [f32; 4]) -> Self {
+        self.with_corner_colors([color; 4])
+    }
+
+    pub fn with_corner_colors(mut self, corner_colors: [[f32; 4]; 4]) -> Self {
+        self.corner_colors = corner_colors;
+        self
+    }
+
+    /// Top to bottom gradient.
+    pub fn with_vertical_gradient(self, top: [f32; 4], bottom: [f32; 4]) -> Self {
+        self.with_corner_colors([top, top, bottom, bottom])
+    }
+
+    /// Left to right gradient.
+    pub fn with_horizontal_gradient(self, left: [f32; 4], right: [f32; 4]) -> Self {
+        self.with_corner_colors([left, right, right, left])
+    }
+
+    pub fn with_uv_transform(mut self, uv_transform: UvTransform) -> Self {
+        self.uv_transform = uv_transform;
+        self
+    }
+
+    /// Maps the unit quad centered at the origin to the sprite in world space.
+    pub(crate) fn model(&self) -> Mat4 {
+        let skew = Mat4::from_cols(
+            Vec4::new(1.0, self.skew.y.tan(), 0.0, 0.0),
+            Vec4::new(self.skew.x.tan(), 1.0, 0.0, 0.0),
+            Vec4::Z,
+            Vec4::W,
+        );
+        Mat4::from_translation(self.position)
+            * Mat4::from_rotation_z(self.angle)
+            * skew
+            * Mat4::from_scale(self.size.extend(1.0))
+            * Mat4::from_translation((Vec2::splat(0.5) - self.pivot).extend(0.0))
+    }
+}
+
+/// How a texture region is laid out inside a quad, see `Engine::render_repeated`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvTransform {
+    /// Times the region is shown across the quad on each axis.
+    pub repeat: Vec2,
+    /// Shift in region sizes, `(0.5, 0.0)` starts halfway through the region horizontally.
+    pub offset: Vec2,
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl UvTransform {
+    /// The region shown once, unshifted.
+    pub const IDENTITY: UvTransform = UvTransform {
+        repeat: Vec2::ONE,
+        offset: Vec2::ZERO,
+    };
+
+    pub fn repeat(x: f32, y: f32) -> Self {
+        Self {
+            repeat: Vec2::new(x, y),
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Moves the offset by `velocity` region sizes per second. The offset is kept in `0..1` so
+    /// it doesn't lose precision over time.
+    pub fn scroll(&mut self, velocity: Vec2, delta_time: f32) {
+        self.offset = (self.offset + velocity * delta_time).rem_euclid(Vec2::ONE);
+    }
+
+    pub(crate) fn to_array(self) -> [f32; 4] {
+        [self.repeat.x, self.repeat.y, self.offset.x, self.offset.y]
+    }
+}
+
+/// A texture plus the rectangle of it to sample. Also what atlases and sprite sheets hand out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureRegion {
+    pub texture: TextureHandle,
+    pub rect: RegionRect,
+}
+
+impl TextureRegion {
+    pub fn whole(texture: TextureHandle) -> Self {
+        Self {
+            texture,
+            rect: RegionRect::FULL,
+        }
+    }
+
+    pub fn from_pixels(texture: TextureHandle, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            texture,
+            rect: RegionRect::Pixels {
+                x,
+                y,
+                width,
+                height,
+            },
+        }
+    }
+
+    pub fn from_normalized(texture: TextureHandle, uv_rect: [f32; 4]) -> Self {
+        Self {
+            texture,
+            rect: RegionRect::Normalized(uv_rect),
+        }
+    }
+
+    /// Size in pixels, only known for pixel rects.
+    pub fn pixel_size(&self) -> Option<(u32, u32)> {
+        match self.rect {
+            RegionRect::Pixels { width, height, .. } => Some((width, height)),
+            RegionRect::Normalized(_) => None,
+        }
+    }
+}
+
+/// How the frames of a sprite sheet are laid out. All values are in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteSheetGrid {
+    pub cell_width: u32,
+    pub cell_height: u32,
+    /// Space between two neighbouring cells.
+    pub padding: u32,
+    /// Space between the border of the texture and the first/last cells.
+    pub margin: u32,
+}
+
+impl SpriteSheetGrid {
+    pub fn new(cell_width: u32, cell_height: u32) -> Self {
+        Self {
+            cell_width,
+            cell_height,
+            padding: 0,
+            margin: 0,
+        }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+}
+
+/// A texture sliced into equally sized frames. Frames are numbered left to right, top to bottom.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    frames: Vec<TextureRegion>,
+    columns: u32,
+    rows: u32,
+}
+
+impl SpriteSheet {
+    /// Slices an already created texture of `texture_width` x `texture_height` pixels.
+    /// Cells that don't fit entirely are left out.
+    pub fn from_grid(
+        texture: TextureHandle,
+        texture_width: u32,
+        texture_height: u32,
+        grid: SpriteSheetGrid,
+    ) -> Self {
+        let count = |size: u32, cell: u32| {
+            let usable = size.saturating_sub(2 * grid.margin);
+            if cell == 0 || usable < cell {
+                0
+            } else {
+                (usable - cell) / (cell + grid.padding) + 1
+            }
+        };
+        let columns = count(texture_width, grid.cell_width);
+        let rows = count(texture_height, grid.cell_height);
+
+        let mut frames = Vec::with_capacity((columns * rows) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                frames.push(TextureRegion::from_pixels(
+                    texture,
+                    grid.margin + column * (grid.cell_width + grid.padding),
+                    grid.margin + row * (grid.cell_height + grid.padding),
+                    grid.cell_width,
+                    grid.cell_height,
+                ));
+            }
+        }
+
+        Self {
+            frames,
+            columns,
+            rows,
+        }
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&TextureRegion> {
+        self.frames.get(index)
+    }
+
+    pub fn frame_at(&self, column: u32, row: u32) -> Option<&TextureRegion> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        self.frames.get((row * self.columns + column) as usize)
+    }
+
+    pub fn frames(&self) -> &[TextureRegion] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+}
+
+impl Engine {
+    /// Creates the texture at `texture_path` under `id` and slices it into frames.
+    pub fn load_sprite_sheet(
+        &mut self,
+        id: String,
+        texture_path: impl AsRef<Path>,
+        grid: SpriteSheetGrid,
+    ) -> Result<SpriteSheet, EngineError> {
+        let texture = self.create_texture(id, texture_path)?;
+        // just created
+        let (width, height) = self.texture_dimensions(texture).unwrap();
+        Ok(SpriteSheet::from_grid(texture, width, height, grid))
+    }
+}
@@ -0,0 +1,641 @@
+use std::ops::Range;
+
+use glam::{Vec2, Vec3};
+
+use crate::font::{Font, Glyph};
+use crate::Engine;
+
+/// Fake bold draws glyphs a second time this far to the right, as a fraction of the font size.
+const BOLD_OFFSET: f32 = 1.0 / 24.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces of wrapped lines to fill the width. The last line of a paragraph
+    /// stays left aligned.
+    Justify,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// How `Engine::layout_text` arranges text.
+///
+/// ```ignore
+/// let layout = TextLayout::new(16.0)
+///     .with_box(Vec2::new(300.0, 120.0))
+///     .with_align(TextAlign::Justify)
+///     .with_markup(true);
+/// engine.render_text_layout("[b]Guard:[/b] Halt! Who goes there?", top_left, &mut font, &layout);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    /// Font size in pixels.
+    pub size: f32,
+    /// Of text outside `[color]` tags.
+    pub color: [f32; 4],
+    /// Lines wrap at spaces to fit, words longer than this are split. `None` only breaks lines
+    /// at `\n`, and aligns them to the widest one.
+    pub max_width: Option<f32>,
+    /// The text is aligned vertically in this height. `None` puts it at the top.
+    pub height: Option<f32>,
+    pub align: TextAlign,
+    pub vertical_align: VerticalAlign,
+    /// Reads `[color=#rrggbb]`, `[b]` and the other tags described at `Engine::layout_text`,
+    /// instead of drawing them.
+    pub markup: bool,
+}
+
+impl TextLayout {
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            color: [1.0; 4],
+            max_width: None,
+            height: None,
+            align: TextAlign::default(),
+            vertical_align: VerticalAlign::default(),
+            markup: false,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Wraps to the box's width and aligns vertically in its height.
+    pub fn with_box(mut self, size: Vec2) -> Self {
+        self.max_width = Some(size.x);
+        self.height = Some(size.y);
+        self
+    }
+
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_vertical_align(mut self, vertical_align: VerticalAlign) -> Self {
+        self.vertical_align = vertical_align;
+        self
+    }
+
+    pub fn with_markup(mut self, markup: bool) -> Self {
+        self.markup = markup;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PlacedGlyph {
+    glyph: Glyph,
+    // pen position on the baseline, from the top-left of the box with y going down
+    pen: Vec2,
+    color: [f32; 4],
+    // 0 when not bold
+    bold_offset: f32,
+}
+
+/// Text arranged by `Engine::layout_text`, ready to be measured and drawn any number of times.
+#[derive(Debug, Clone)]
+pub struct LaidOutText {
+    glyphs: Vec<PlacedGlyph>,
+    size: Vec2,
+    line_count: usize,
+}
+
+impl LaidOutText {
+    /// Width of the widest line and height of all lines, in pixels.
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Style {
+    color: [f32; 4],
+    bold: bool,
+}
+
+impl Style {
+    fn current(colors: &[[f32; 4]], bold_depth: u32) -> Self {
+        Self {
+            color: *colors.last().unwrap(),
+            bold: bold_depth > 0,
+        }
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa` in sRGB, like in CSS.
+fn parse_hex_color(hex: &str) -> Option<[f32; 4]> {
+    let hex = hex.strip_prefix('#')?;
+    // `from_str_radix` alone would also take a leading `+`
+    if !matches!(hex.len(), 6 | 8) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| {
+        u8::from_str_radix(hex.get(index * 2..index * 2 + 2)?, 16)
+            .ok()
+            .map(|value| value as f32 / 255.0)
+    };
+    let to_linear = |value: f32| {
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let alpha = if hex.len() == 8 { channel(3)? } else { 1.0 };
+    Some([
+        to_linear(channel(0)?),
+        to_linear(channel(1)?),
+        to_linear(channel(2)?),
+        alpha,
+    ])
+}
+
+/// Splits `text` into characters and their styles. Anything in brackets that isn't a known tag
+/// is kept as text.
+fn parse_markup(text: &str, color: [f32; 4]) -> Vec<(char, Style)> {
+    let mut chars = vec![];
+    let mut colors = vec![color];
+    let mut bold_depth = 0u32;
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("[[") {
+            chars.push(('[', Style::current(&colors, bold_depth)));
+            rest = after;
+            continue;
+        }
+        if let Some((tag, after)) = rest.strip_prefix('[').and_then(|tag| tag.split_once(']')) {
+            let known = match tag {
+                "b" => {
+                    bold_depth += 1;
+                    true
+                }
+                "/b" => {
+                    bold_depth = bold_depth.saturating_sub(1);
+                    true
+                }
+                // the layout's own color is never popped
+                "/color" => {
+                    if colors.len() > 1 {
+                        colors.pop();
+                    }
+                    true
+                }
+                _ => match tag.strip_prefix("color=").and_then(parse_hex_color) {
+                    Some(color) => {
+                        colors.push(color);
+                        true
+                    }
+                    None => false,
+                },
+            };
+            if known {
+                rest = after;
+                continue;
+            }
+        }
+        chars.push((ch, Style::current(&colors, bold_depth)));
+        rest = &rest[ch.len_utf8()..];
+    }
+    chars
+}
+
+/// Splits styled text at line breaks, `\n` or `\r\n`.
+fn paragraphs(styled: &[(char, Style)]) -> impl Iterator<Item = &[(char, Style)]> {
+    styled
+        .split(|(ch, _)| *ch == '\n')
+        .map(|paragraph| match paragraph.split_last() {
+            Some((('\r', _), rest)) => rest,
+            _ => paragraph,
+        })
+}
+
+struct Item {
+    ch: char,
+    style: Style,
+    glyph: Option<Glyph>,
+    // with the previous character of the paragraph
+    kerning: f32,
+    advance: f32,
+}
+
+struct Line {
+    items: Range<usize>,
+    width: f32,
+    // ended by wrapping rather than by the end of a paragraph
+    wrapped: bool,
+}
+
+/// Breaks the items of one paragraph into lines no wider than `max_width`, at spaces when it
+/// can and inside words when a word is wider than a line on its own.
+fn wrap(items: &[Item], paragraph: Range<usize>, max_width: f32, lines: &mut Vec<Line>) {
+    let item_width = |index: usize, line_start: usize| {
+        let item: &Item = &items[index];
+        // kerning doesn't carry over to a new line
+        if index == line_start {
+            item.advance
+        } else {
+            item.kerning + item.advance
+        }
+    };
+
+    let mut line_start = paragraph.start;
+    // end of the last word on the line, trailing spaces are left out
+    let mut line_end = paragraph.start;
+    let mut width = 0.0;
+    let mut index = paragraph.start;
+    while index < paragraph.end {
+        let space_start = index;
+        while index < paragraph.end && items[index].ch.is_whitespace() {
+            index += 1;
+        }
+        let word_start = index;
+        while index < paragraph.end && !items[index].ch.is_whitespace() {
+            index += 1;
+        }
+        if word_start == index {
+            break;
+        }
+
+        let spaced_word_width: f32 = (space_start..index)
+            .map(|index| item_width(index, line_start))
+            .sum();
+        if width + spaced_word_width > max_width {
+            // a line of nothing but indentation is dropped rather than kept empty
+            if line_end > line_start {
+                lines.push(Line {
+                    items: line_start..line_end,
+                    width,
+                    wrapped: true,
+                });
+            }
+            line_start = word_start;
+            width = 0.0;
+        } else {
+            width += (space_start..word_start)
+                .map(|index| item_width(index, line_start))
+                .sum::<f32>();
+        }
+
+        for char_index in word_start..index {
+            if char_index > line_start && width + item_width(char_index, line_start) > max_width {
+                lines.push(Line {
+                    items: line_start..char_index,
+                    width,
+                    wrapped: true,
+                });
+                line_start = char_index;
+                width = 0.0;
+            }
+            width += item_width(char_index, line_start);
+        }
+        line_end = index;
+    }
+    lines.push(Line {
+        items: line_start..line_end,
+        width,
+        wrapped: false,
+    });
+}
+
+impl Engine {
+    /// Wraps, aligns and styles `text`, to be drawn with `render_laid_out_text` or just
+    /// measured. Every `\n` or `\r\n` starts a new paragraph.
+    ///
+    /// With `layout.markup` these tags style the text between them, and nest:
+    /// - `[color=#ff0000]...[/color]`, `#rrggbb` or `#rrggbbaa` in sRGB like in CSS.
+    /// - `[b]...[/b]` for bold, drawn by doubling up glyphs since fonts have one weight.
+    /// - `[[` for a literal `[`.
+    pub fn layout_text(
+        &mut self,
+        text: &str,
+        font: &mut (impl Font + ?Sized),
+        layout: &TextLayout,
+    ) -> LaidOutText {
+        let size = layout.size;
+        let styled = if layout.markup {
+            parse_markup(text, layout.color)
+        } else {
+            let style = Style {
+                color: layout.color,
+                bold: false,
+            };
+            text.chars().map(|ch| (ch, style)).collect()
+        };
+
+        let mut items = vec![];
+        let mut lines = vec![];
+        for paragraph in paragraphs(&styled) {
+            let start = items.len();
+            let mut previous = None;
+            for &(ch, style) in paragraph {
+                let kerning = previous.map_or(0.0, |previous| font.kerning(previous, ch, size));
+                previous = Some(ch);
+                let glyph = font.glyph(self, ch, size);
+                let bold_offset = if style.bold { size * BOLD_OFFSET } else { 0.0 };
+                items.push(Item {
+                    ch,
+                    style,
+                    glyph,
+                    kerning,
+                    advance: glyph.map_or(0.0, |glyph| glyph.advance + bold_offset),
+                });
+            }
+            wrap(
+                &items,
+                start..items.len(),
+                layout.max_width.unwrap_or(f32::INFINITY),
+                &mut lines,
+            );
+        }
+
+        let metrics = font.line_metrics(size);
+        let text_width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+        let text_height = lines.len() as f32 * metrics.line_height;
+        let box_width = layout.max_width.unwrap_or(text_width);
+        let top = match (layout.height, layout.vertical_align) {
+            (Some(height), VerticalAlign::Middle) => (height - text_height) / 2.0,
+            (Some(height), VerticalAlign::Bottom) => height - text_height,
+            _ => 0.0,
+        };
+
+        let mut glyphs = vec![];
+        for (line_index, line) in lines.iter().enumerate() {
+            let line_items = &items[line.items.clone()];
+            // only spaces after the first word stretch, leading ones are indentation
+            let first_word = line_items
+                .iter()
+                .position(|item| !item.ch.is_whitespace())
+                .unwrap_or(line_items.len());
+            let spaces = line_items[first_word..]
+                .iter()
+                .filter(|item| item.ch.is_whitespace())
+                .count();
+
+            let free_width = box_width - line.width;
+            let (mut pen_x, space_stretch) = match layout.align {
+                TextAlign::Left => (0.0, 0.0),
+                TextAlign::Center => (free_width / 2.0, 0.0),
+                TextAlign::Right => (free_width, 0.0),
+                TextAlign::Justify if line.wrapped && spaces > 0 => {
+                    (0.0, free_width.max(0.0) / spaces as f32)
+                }
+                TextAlign::Justify => (0.0, 0.0),
+            };
+            let baseline = top + metrics.ascent + line_index as f32 * metrics.line_height;
+            for (index, item) in line_items.iter().enumerate() {
+                if index > 0 {
+                    pen_x += item.kerning;
+                }
+                if let Some(glyph) = item.glyph {
+                    glyphs.push(PlacedGlyph {
+                        glyph,
+                        pen: Vec2::new(pen_x, baseline),
+                        color: item.style.color,
+                        bold_offset: item.advance - glyph.advance,
+                    });
+                }
+                pen_x += item.advance;
+                if index > first_word && item.ch.is_whitespace() {
+                    pen_x += space_stretch;
+                }
+            }
+        }
+
+        LaidOutText {
+            glyphs,
+            size: Vec2::new(text_width, text_height),
+            line_count: lines.len(),
+        }
+    }
+
+    /// Draws `text` with the top-left corner of its box at `position`.
+    pub fn render_laid_out_text(&mut self, text: &LaidOutText, position: Vec3) {
+        for placed in &text.glyphs {
+            let pen = Vec3::new(
+                position.x + placed.pen.x,
+                position.y - placed.pen.y,
+                position.z,
+            );
+            self.push_glyph(&placed.glyph, pen, placed.color);
+            if placed.bold_offset > 0.0 {
+                let bold_pen = pen + Vec3::new(placed.bold_offset, 0.0, 0.0);
+                self.push_glyph(&placed.glyph, bold_pen, placed.color);
+            }
+        }
+    }
+
+    /// Lays out and draws `text` in one go, see `layout_text`. Returns the text's size.
+    pub fn render_text_layout(
+        &mut self,
+        text: &str,
+        position: Vec3,
+        font: &mut (impl Font + ?Sized),
+        layout: &TextLayout,
+    ) -> Vec2 {
+        let laid_out = self.layout_text(text, font, layout);
+        self.render_laid_out_text(&laid_out, position);
+        laid_out.size()
+    }
+
+    /// Width of the widest line and height of all lines of `text`, see `layout_text`.
+    pub fn measure_text(
+        &mut self,
+        text: &str,
+        font: &mut (impl Font + ?Sized),
+        layout: &TextLayout,
+    ) -> Vec2 {
+        self.layout_text(text, font, layout).size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0; 4];
+    const PLAIN: Style = Style {
+        color: WHITE,
+        bold: false,
+    };
+
+    /// Every character one pixel wide.
+    fn items(text: &str) -> Vec<Item> {
+        text.chars()
+            .map(|ch| Item {
+                ch,
+                style: PLAIN,
+                glyph: None,
+                kerning: 0.0,
+                advance: 1.0,
+            })
+            .collect()
+    }
+
+    fn wrapped(items: &[Item], max_width: f32) -> Vec<(String, f32, bool)> {
+        let mut lines = vec![];
+        wrap(items, 0..items.len(), max_width, &mut lines);
+        lines
+            .into_iter()
+            .map(|line| {
+                let text = items[line.items].iter().map(|item| item.ch).collect();
+                (text, line.width, line.wrapped)
+            })
+            .collect()
+    }
+
+    fn text(styled: &[(char, Style)]) -> String {
+        styled.iter().map(|(ch, _)| ch).collect()
+    }
+
+    #[test]
+    fn wrap_at_spaces() {
+        assert_eq!(
+            wrapped(&items("aaa bbb ccc"), 7.0),
+            [("aaa bbb".into(), 7.0, true), ("ccc".into(), 3.0, false)]
+        );
+    }
+
+    #[test]
+    fn wrap_drops_trailing_spaces() {
+        assert_eq!(
+            wrapped(&items("aa   bb  "), 4.0),
+            [("aa".into(), 2.0, true), ("bb".into(), 2.0, false)]
+        );
+    }
+
+    #[test]
+    fn wrap_splits_long_words() {
+        assert_eq!(
+            wrapped(&items("abcdefgh"), 3.0),
+            [
+                ("abc".into(), 3.0, true),
+                ("def".into(), 3.0, true),
+                ("gh".into(), 2.0, false),
+            ]
+        );
+        assert_eq!(
+            wrapped(&items("ab cdefgh"), 4.0),
+            [
+                ("ab".into(), 2.0, true),
+                ("cdef".into(), 4.0, true),
+                ("gh".into(), 2.0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_indentation() {
+        assert_eq!(wrapped(&items("  ab"), 10.0), [("  ab".into(), 4.0, false)]);
+        // indentation alone on a line isn't kept as an empty line
+        assert_eq!(
+            wrapped(&items("   abcd"), 4.0),
+            [("abcd".into(), 4.0, false)]
+        );
+    }
+
+    #[test]
+    fn wrap_drops_kerning_at_line_start() {
+        let mut items = items("ab cd");
+        for item in &mut items[1..] {
+            item.kerning = -0.5;
+        }
+        assert_eq!(
+            wrapped(&items, 2.5),
+            [("ab".into(), 1.5, true), ("cd".into(), 1.5, false)]
+        );
+    }
+
+    #[test]
+    fn wrap_empty_paragraph() {
+        assert_eq!(wrapped(&items(""), 10.0), [(String::new(), 0.0, false)]);
+        assert_eq!(wrapped(&items("   "), 10.0), [(String::new(), 0.0, false)]);
+    }
+
+    #[test]
+    fn paragraphs_split_on_lf_and_crlf() {
+        let styled: Vec<_> = "a\r\nb\nc\r\n".chars().map(|ch| (ch, PLAIN)).collect();
+        let paragraphs: Vec<_> = paragraphs(&styled).map(text).collect();
+        assert_eq!(paragraphs, ["a", "b", "c", ""]);
+    }
+
+    #[test]
+    fn markup_nested_tags() {
+        let red = parse_hex_color("#ff0000").unwrap();
+        let styled = parse_markup("[b]a[color=#ff0000]b[/b]c[/color]d", WHITE);
+        assert_eq!(text(&styled), "abcd");
+        let styles: Vec<_> = styled
+            .iter()
+            .map(|(_, style)| (style.color, style.bold))
+            .collect();
+        assert_eq!(
+            styles,
+            [(WHITE, true), (red, true), (red, false), (WHITE, false)]
+        );
+    }
+
+    #[test]
+    fn markup_unclosed_and_unbalanced_tags() {
+        let styled = parse_markup("[b]ab", WHITE);
+        assert!(styled.iter().all(|(_, style)| style.bold));
+
+        // closing more than was opened keeps the layout's color and doesn't underflow
+        let styled = parse_markup("[/color][/b]a[b]b", WHITE);
+        assert_eq!(text(&styled), "ab");
+        assert_eq!(styled[0].1, PLAIN);
+        assert!(styled[1].1.bold);
+    }
+
+    #[test]
+    fn markup_escaped_bracket() {
+        let styled = parse_markup("[[b]x", WHITE);
+        assert_eq!(text(&styled), "[b]x");
+        assert!(styled.iter().all(|(_, style)| *style == PLAIN));
+    }
+
+    #[test]
+    fn markup_keeps_unknown_tags() {
+        for source in ["[i]x[/i]", "[color=red]x", "[color=#ff00]x", "[b x", "a]b"] {
+            assert_eq!(text(&parse_markup(source, WHITE)), source);
+        }
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_hex_color("#ffffff"), Some([1.0; 4]));
+        assert_eq!(parse_hex_color("#00000000"), Some([0.0; 4]));
+        let [r, g, b, a] = parse_hex_color("#80808080").unwrap();
+        // sRGB 128 is about 0.216 linear, alpha stays linear
+        assert!((r - 0.2158).abs() < 0.001);
+        assert_eq!((r, r), (g, b));
+        assert_eq!(a, 128.0 / 255.0);
+    }
+
+    #[test]
+    fn invalid_hex_colors() {
+        for hex in ["ffffff", "#fff", "#fffffff", "#gggggg", "#ffffé", "#+fffff"] {
+            assert_eq!(parse_hex_color(hex), None, "{hex}");
+        }
+    }
+}
@@ -1,6 +1,179 @@
-use anyhow::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Weak};
+
+use anyhow::Result;
 use image::GenericImageView;
 
+use crate::error::EngineError;
+use crate::mipmap::mip_level_count;
+use crate::BindableTexture;
+
+/// Reads and decodes an image. The format comes from the file's contents, or its extension for
+/// formats without a signature (TGA).
+pub fn load_image(path: &Path) -> Result<image::DynamicImage, EngineError> {
+    let bytes = std::fs::read(path).map_err(|source| EngineError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let decode_error = |source| EngineError::ImageDecode {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let format = image::guess_format(&bytes)
+        .or_else(|_| image::ImageFormat::from_path(path))
+        .map_err(decode_error)?;
+    if !format.reading_enabled() {
+        return Err(EngineError::UnsupportedImageFormat {
+            path: path.to_path_buf(),
+            format,
+            feature: format_feature(format),
+        });
+    }
+    image::load_from_memory_with_format(&bytes, format).map_err(decode_error)
+}
+
+/// The cargo feature that enables `format`.
+fn format_feature(format: image::ImageFormat) -> Option<&'static str> {
+    match format {
+        image::ImageFormat::Bmp => Some("bmp"),
+        image::ImageFormat::Tga => Some("tga"),
+        image::ImageFormat::Gif => Some("gif"),
+        image::ImageFormat::WebP => Some("webp"),
+        image::ImageFormat::Qoi => Some("qoi"),
+        _ => None,
+    }
+}
+
+/// How a texture is filtered and what happens outside of `0..1`.
+///
+/// `SamplerOptions::pixel_art()` keeps hard pixel edges, `SamplerOptions::smooth()` filters
+/// everything linearly. The default filters linearly when magnifying only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplerOptions {
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    /// Maximum anisotropy, 1 disables it. Only used when every filter is linear.
+    pub anisotropy: u16,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            anisotropy: 1,
+        }
+    }
+}
+
+impl SamplerOptions {
+    pub fn pixel_art() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    pub fn smooth() -> Self {
+        Self {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        }
+    }
+
+    /// Same address mode on both axes, e.g. `wgpu::AddressMode::Repeat` for tiling.
+    pub fn with_address_mode(mut self, address_mode: wgpu::AddressMode) -> Self {
+        self.address_mode_u = address_mode;
+        self.address_mode_v = address_mode;
+        self
+    }
+
+    /// Repeats the texture outside of `0..1` on both axes, for `Engine::render_repeated`.
+    pub fn with_repeat(self) -> Self {
+        self.with_address_mode(wgpu::AddressMode::Repeat)
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: u16) -> Self {
+        self.anisotropy = anisotropy;
+        self
+    }
+
+    pub(crate) fn create_sampler(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        let all_linear = self.mag_filter == wgpu::FilterMode::Linear
+            && self.min_filter == wgpu::FilterMode::Linear
+            && self.mipmap_filter == wgpu::FilterMode::Linear;
+        // wgpu rejects anisotropy with any nearest filter
+        let anisotropy = if all_linear {
+            self.anisotropy.clamp(1, 16)
+        } else {
+            if self.anisotropy > 1 {
+                log::warn!("Anisotropic filtering needs linear filters only, disabling it");
+            }
+            1
+        };
+
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp: anisotropy,
+            ..Default::default()
+        })
+    }
+}
+
+/// Everything chosen when a texture is created. A `SamplerOptions` converts into this with the
+/// rest left at the defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextureOptions {
+    pub sampler: SamplerOptions,
+    /// Generates the full mip chain on upload and minifies trilinearly, so sprites drawn
+    /// much smaller than their size don't shimmer.
+    pub mipmaps: bool,
+}
+
+impl TextureOptions {
+    pub fn with_mipmaps(mut self) -> Self {
+        self.mipmaps = true;
+        self
+    }
+
+    /// The sampler actually created, minification is switched to trilinear with mipmaps.
+    pub(crate) fn effective_sampler(&self) -> SamplerOptions {
+        let mut sampler = self.sampler;
+        if self.mipmaps {
+            sampler.min_filter = wgpu::FilterMode::Linear;
+            sampler.mipmap_filter = wgpu::FilterMode::Linear;
+        }
+        sampler
+    }
+}
+
+impl From<SamplerOptions> for TextureOptions {
+    fn from(sampler: SamplerOptions) -> Self {
+        Self {
+            sampler,
+            ..Default::default()
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct Texture {
     pub texture: wgpu::Texture,
@@ -10,23 +183,32 @@ pub struct Texture {
 }
 
 impl Texture {
+    /// The format is guessed from `bytes`, so TGA (which has no signature) isn't recognized.
     #[allow(dead_code)]
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         bytes: &[u8],
         label: &str,
-    ) -> Result<Self> {
+    ) -> Result<Self, image::ImageError> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, Some(label))
+        Ok(Self::from_image(
+            device,
+            queue,
+            &img,
+            Some(label),
+            &TextureOptions::default(),
+        ))
     }
 
+    /// With `options.mipmaps` only level 0 is uploaded, the rest is left to `MipmapGenerator`.
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
-    ) -> Result<Self> {
+        options: &TextureOptions,
+    ) -> Self {
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
@@ -35,15 +217,28 @@ impl Texture {
             height: dimensions.1,
             depth_or_array_layers: 1,
         };
+        let (mip_level_count, usage) = if options.mipmaps {
+            (
+                mip_level_count(dimensions.0, dimensions.1),
+                wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        } else {
+            (
+                1,
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            )
+        };
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size: texture_extent,
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
             view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            usage,
         });
 
         // dimensions are 256 x 256 for `happy-tree.png`
@@ -66,22 +261,14 @@ impl Texture {
         );
 
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
+        let texture_sampler = options.effective_sampler().create_sampler(device);
 
-        Ok(Self {
+        Self {
             texture,
             texture_view,
             texture_sampler,
             texture_extent,
-        })
+        }
     }
 
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float; // 1.
@@ -133,67 +320,306 @@ impl Texture {
             texture_extent,
         }
     }
+}
 
-    pub fn from_dimensions(
-        device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-        // queue: &wgpu::Queue,
-        label: Option<&str>,
-    ) -> Result<Self> {
-        let dimensions = (config.width, config.height);
-        let texture_extent = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
-        };
+/// Cheap reference to a texture created by the `Engine`, use it for drawing instead of the name.
+/// Handles to removed textures don't point at anything, even once the slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle {
+    index: u32,
+    generation: u32,
+}
 
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label,
-            size: texture_extent,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
-            // format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-            usage: wgpu::TextureUsages::TEXTURE_BINDING
-                | wgpu::TextureUsages::COPY_DST
-                | wgpu::TextureUsages::STORAGE_BINDING,
+impl TextureHandle {
+    /// Points at no texture.
+    pub(crate) const INVALID: TextureHandle = TextureHandle {
+        index: u32::MAX,
+        generation: 0,
+    };
+}
+
+/// A reference-counted `TextureHandle` from `Engine::share_texture`. The texture is removed
+/// once the last clone is dropped, at the start of the next frame.
+#[derive(Debug, Clone)]
+pub struct SharedTexture {
+    inner: Arc<SharedTextureInner>,
+}
+
+impl SharedTexture {
+    pub fn handle(&self) -> TextureHandle {
+        self.inner.handle
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SharedTextureInner {
+    handle: TextureHandle,
+    release: Sender<TextureHandle>,
+}
+
+impl Drop for SharedTextureInner {
+    fn drop(&mut self) {
+        // The engine is gone already if this fails, and the texture with it.
+        let _ = self.release.send(self.handle);
+    }
+}
+
+/// Snapshot of the textures alive, from `Engine::texture_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextureStats {
+    pub count: usize,
+    /// Estimated from the size, format and mip levels of every texture.
+    pub gpu_bytes: u64,
+}
+
+struct TextureEntry {
+    name: String,
+    // `None` until a background load finishes
+    texture: Option<BindableTexture>,
+    // the latest background load of this texture, while it's in flight
+    load_job: Option<u64>,
+    // file the texture was loaded from, for reloading
+    source: Option<PathBuf>,
+    // set while `SharedTexture`s to it are alive
+    shared: Weak<SharedTextureInner>,
+}
+
+struct TextureSlot {
+    // bumped on removal so old handles stop matching
+    generation: u32,
+    entry: Option<TextureEntry>,
+}
+
+/// Textures indexed by `TextureHandle`, with a name index on the side for lookups by id.
+#[derive(Default)]
+pub(crate) struct TextureStore {
+    slots: Vec<TextureSlot>,
+    free_slots: Vec<u32>,
+    names: HashMap<String, TextureHandle>,
+    // bumped whenever a texture is added, replaced, changed or removed
+    version: u64,
+    // id for the next background load
+    next_load_job: u64,
+}
+
+impl TextureStore {
+    fn entry(&self, handle: TextureHandle) -> Option<&TextureEntry> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.entry.as_ref())
+    }
+
+    fn entry_mut(&mut self, handle: TextureHandle) -> Option<&mut TextureEntry> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.entry.as_mut())
+    }
+
+    pub(crate) fn get(&self, handle: TextureHandle) -> Option<&BindableTexture> {
+        self.entry(handle).and_then(|entry| entry.texture.as_ref())
+    }
+
+    pub(crate) fn get_mut(&mut self, handle: TextureHandle) -> Option<&mut BindableTexture> {
+        self.version += 1;
+        self.entry_mut(handle)
+            .and_then(|entry| entry.texture.as_mut())
+    }
+
+    /// Changes whenever the textures do, for caching things built from them.
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub(crate) fn handle(&self, name: &str) -> Option<TextureHandle> {
+        self.names.get(name).copied()
+    }
+
+    pub(crate) fn name(&self, handle: TextureHandle) -> Option<&str> {
+        self.entry(handle).map(|entry| entry.name.as_str())
+    }
+
+    pub(crate) fn source(&self, handle: TextureHandle) -> Option<&Path> {
+        self.entry(handle).and_then(|entry| entry.source.as_deref())
+    }
+
+    /// Every texture loaded from a file, with that file.
+    pub(crate) fn sources(&self) -> impl Iterator<Item = (TextureHandle, &Path)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = TextureHandle {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            let entry = slot
+                .entry
+                .as_ref()
+                .filter(|entry| entry.texture.is_some())?;
+            Some((handle, entry.source.as_deref()?))
+        })
+    }
+
+    /// Adds `texture` as `name`. A texture already called `name` is replaced in place so its
+    /// handle keeps working.
+    pub(crate) fn insert(
+        &mut self,
+        name: String,
+        texture: BindableTexture,
+        source: Option<PathBuf>,
+    ) -> TextureHandle {
+        self.version += 1;
+        if let Some(handle) = self.handle(&name) {
+            // names only point at live entries
+            let entry = self.entry_mut(handle).unwrap();
+            entry.texture = Some(texture);
+            entry.source = source;
+            entry.load_job = None;
+            return handle;
+        }
+
+        self.insert_entry(TextureEntry {
+            name,
+            texture: Some(texture),
+            load_job: None,
+            source,
+            shared: Weak::new(),
+        })
+    }
+
+    /// Handle and job id for a texture that is about to be loaded in the background. An
+    /// existing texture called `name` keeps being drawn until the load finishes, and only the
+    /// latest load of a texture counts.
+    pub(crate) fn reserve(&mut self, name: String) -> (TextureHandle, u64) {
+        let load_job = self.next_load_job;
+        self.next_load_job += 1;
+        if let Some(handle) = self.handle(&name) {
+            self.entry_mut(handle).unwrap().load_job = Some(load_job);
+            return (handle, load_job);
+        }
+
+        let handle = self.insert_entry(TextureEntry {
+            name,
+            texture: None,
+            load_job: Some(load_job),
+            source: None,
+            shared: Weak::new(),
         });
+        (handle, load_job)
+    }
 
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let texture_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            anisotropy_clamp: 1,
-            // anisotropy_clamp: std::num::NonZeroU8::new(1),
-            ..Default::default()
+    pub(crate) fn is_loading(&self, handle: TextureHandle) -> bool {
+        self.entry(handle)
+            .is_some_and(|entry| entry.load_job.is_some())
+    }
+
+    /// `true` while `load_job` is the latest load of the texture and hasn't finished.
+    pub(crate) fn is_current_load(&self, handle: TextureHandle, load_job: u64) -> bool {
+        self.entry(handle)
+            .is_some_and(|entry| entry.load_job == Some(load_job))
+    }
+
+    /// The background load `load_job` failed. A previous texture is kept, otherwise the entry
+    /// goes away. Does nothing if a newer load was started since.
+    pub(crate) fn cancel_loading(&mut self, handle: TextureHandle, load_job: u64) {
+        if !self.is_current_load(handle, load_job) {
+            return;
+        }
+        let entry = self.entry_mut(handle).unwrap();
+        entry.load_job = None;
+        if entry.texture.is_none() {
+            self.remove(handle);
+        }
+    }
+
+    fn insert_entry(&mut self, entry: TextureEntry) -> TextureHandle {
+        let name = entry.name.clone();
+        let handle = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.entry = Some(entry);
+                TextureHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(TextureSlot {
+                    generation: 0,
+                    entry: Some(entry),
+                });
+                TextureHandle {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        };
+        self.names.insert(name, handle);
+        handle
+    }
+
+    /// Returns `false` if `handle` didn't point at a texture.
+    pub(crate) fn remove(&mut self, handle: TextureHandle) -> bool {
+        if self.entry(handle).is_none() {
+            return false;
+        }
+
+        self.version += 1;
+        let slot = &mut self.slots[handle.index as usize];
+        let entry = slot.entry.take().unwrap();
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(handle.index);
+        self.names.remove(&entry.name);
+        true
+    }
+
+    /// The live `SharedTexture` for `handle`, or a new one releasing through `release`.
+    pub(crate) fn share(
+        &mut self,
+        handle: TextureHandle,
+        release: &Sender<TextureHandle>,
+    ) -> Option<SharedTexture> {
+        let entry = self.entry_mut(handle)?;
+        if let Some(inner) = entry.shared.upgrade() {
+            return Some(SharedTexture { inner });
+        }
+
+        let inner = Arc::new(SharedTextureInner {
+            handle,
+            release: release.clone(),
         });
+        entry.shared = Arc::downgrade(&inner);
+        Some(SharedTexture { inner })
+    }
 
-        // queue.write_texture(
-        //     wgpu::ImageCopyTexture {
-        //         aspect: wgpu::TextureAspect::All,
-        //         texture: &texture,
-        //         mip_level: 0,
-        //         origin: wgpu::Origin3d::ZERO,
-        //     },
-        //     &rgba,
-        //     wgpu::ImageDataLayout {
-        //         offset: 0,
-        //         bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-        //         rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-        //     },
-        //     size,
-        // );
-
-        Ok(Self {
-            texture,
-            texture_view,
-            texture_sampler,
-            texture_extent,
-        })
+    /// Whether `SharedTexture`s keep this texture alive.
+    pub(crate) fn is_shared(&self, handle: TextureHandle) -> bool {
+        self.entry(handle)
+            .is_some_and(|entry| entry.shared.strong_count() > 0)
     }
+
+    pub(crate) fn stats(&self) -> TextureStats {
+        let mut stats = TextureStats::default();
+        let textures = self
+            .slots
+            .iter()
+            .filter_map(|slot| slot.entry.as_ref()?.texture.as_ref());
+        for texture in textures {
+            stats.count += 1;
+            stats.gpu_bytes += gpu_bytes(&texture.texture.texture);
+        }
+        stats
+    }
+}
+
+fn gpu_bytes(texture: &wgpu::Texture) -> u64 {
+    let block_size = texture.format().block_copy_size(None).unwrap_or(4) as u64;
+    let (block_width, block_height) = texture.format().block_dimensions();
+    let size = texture.size();
+    (0..texture.mip_level_count())
+        .map(|mip_level| {
+            let width = (size.width >> mip_level).max(1).div_ceil(block_width) as u64;
+            let height = (size.height >> mip_level).max(1).div_ceil(block_height) as u64;
+            width * height * block_size * size.depth_or_array_layers as u64
+        })
+        .sum()
 }
@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use crate::texture::load_image;
+use crate::{Engine, EngineError, TextureHandle, TextureOptions};
+
+/// Upper bound on decoding threads, more than this mostly fights over disk reads.
+const MAX_WORKERS: usize = 4;
+
+struct LoadJob {
+    handle: TextureHandle,
+    // only the latest job of a texture is applied
+    id: u64,
+    path: PathBuf,
+    options: TextureOptions,
+}
+
+struct LoadResult {
+    job: LoadJob,
+    image: Result<image::DynamicImage, EngineError>,
+}
+
+/// How far along the background texture loads are. Counts start over once every queued load
+/// has finished and a new one is queued.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureLoadProgress {
+    /// Loads that finished, successfully or not.
+    pub finished: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl TextureLoadProgress {
+    pub fn is_done(&self) -> bool {
+        self.finished == self.total
+    }
+
+    /// From `0.0` to `1.0`, `1.0` when nothing was queued.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.finished as f32 / self.total as f32
+        }
+    }
+}
+
+/// Decodes images on worker threads. The GPU upload happens on the main thread in
+/// `Engine::poll_texture_loads`.
+pub(crate) struct TextureLoader {
+    jobs: Sender<LoadJob>,
+    results: Receiver<LoadResult>,
+    progress: TextureLoadProgress,
+}
+
+impl TextureLoader {
+    fn new() -> Self {
+        let (jobs, job_receiver) = std::sync::mpsc::channel::<LoadJob>();
+        let (result_sender, results) = std::sync::mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(MAX_WORKERS);
+        for i in 0..workers {
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            std::thread::Builder::new()
+                .name(format!("texture loader {}", i))
+                .spawn(move || loop {
+                    // the lock is released before decoding so the other workers can take jobs
+                    let job = job_receiver.lock().unwrap().recv();
+                    // the engine was dropped
+                    let Ok(job) = job else {
+                        break;
+                    };
+                    let image = load_image(&job.path);
+                    if result_sender.send(LoadResult { job, image }).is_err() {
+                        break;
+                    }
+                })
+                .expect("Couldn't spawn a texture loader thread");
+        }
+
+        Self {
+            jobs,
+            results,
+            progress: TextureLoadProgress::default(),
+        }
+    }
+}
+
+impl Engine {
+    /// Like `create_texture`, but the file is read and decoded on a background thread so the
+    /// window keeps responding. The returned handle draws the placeholder texture (or the
+    /// texture previously called `id`) until the load finishes.
+    /// A failed load is logged and the handle stops pointing at a texture.
+    pub fn load_texture_async(
+        &mut self,
+        id: String,
+        texture_path: impl AsRef<Path>,
+    ) -> TextureHandle {
+        self.load_texture_async_with_options(id, texture_path, TextureOptions::default())
+    }
+
+    pub fn load_texture_async_with_options(
+        &mut self,
+        id: String,
+        texture_path: impl AsRef<Path>,
+        options: impl Into<TextureOptions>,
+    ) -> TextureHandle {
+        let loader = self.texture_loader.get_or_insert_with(TextureLoader::new);
+        if loader.progress.is_done() {
+            loader.progress = TextureLoadProgress::default();
+        }
+        loader.progress.total += 1;
+
+        let (handle, job_id) = self.textures.reserve(id);
+        let job = LoadJob {
+            handle,
+            id: job_id,
+            path: texture_path.as_ref().to_path_buf(),
+            options: options.into(),
+        };
+        // the workers only stop once the sender is dropped
+        loader.jobs.send(job).unwrap();
+        handle
+    }
+
+    pub fn texture_load_progress(&self) -> TextureLoadProgress {
+        self.texture_loader
+            .as_ref()
+            .map(|loader| loader.progress)
+            .unwrap_or_default()
+    }
+
+    /// `true` while a background load of this texture hasn't finished.
+    pub fn is_texture_loading(&self, handle: TextureHandle) -> bool {
+        self.textures.is_loading(handle)
+    }
+
+    /// Uploads the images decoded since the last call. Called once per frame by the runner.
+    pub(crate) fn poll_texture_loads(&mut self) {
+        let Some(loader) = &mut self.texture_loader else {
+            return;
+        };
+
+        let mut finished = vec![];
+        while let Ok(result) = loader.results.try_recv() {
+            loader.progress.finished += 1;
+            finished.push(result);
+        }
+
+        let mut failed = 0;
+        for LoadResult { job, image } in finished {
+            // removed, replaced by a synchronous load or loaded again in the meantime
+            if !self.textures.is_current_load(job.handle, job.id) {
+                failed += usize::from(image.is_err());
+                continue;
+            }
+            // decoded images can still be too large for the GPU
+            let uploaded = image.and_then(|image| {
+                let id = self.textures.name(job.handle).unwrap().to_string();
+                self.upload_image(id, &image, job.options, Some(job.path))
+            });
+            if let Err(err) = uploaded {
+                log::warn!("Couldn't load texture: {}", err);
+                self.textures.cancel_loading(job.handle, job.id);
+                failed += 1;
+            }
+        }
+        // the loader was there a moment ago
+        self.texture_loader.as_mut().unwrap().progress.failed += failed;
+    }
+}
@@ -0,0 +1,237 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use ab_glyph::{Font as _, FontVec, GlyphId, PxScale, ScaleFont};
+use glam::Vec2;
+
+use crate::atlas::ShelfPacker;
+use crate::font::{Font, Glyph, LineMetrics};
+use crate::{Engine, EngineError, RegionRect, TextureHandle, TextureRegion};
+
+/// Size of the glyph atlas pages, glyphs bigger than this aren't drawn.
+const PAGE_SIZE: u32 = 1024;
+/// Keeps linear filtering from picking up neighbouring glyphs.
+const GLYPH_PADDING: u32 = 1;
+
+struct GlyphPage {
+    texture: TextureHandle,
+    packer: ShelfPacker,
+}
+
+/// A `.ttf` or `.otf` font. Glyphs are rasterized the first time they're drawn at a size and
+/// kept in atlas textures called `"{id}-glyphs-{n}"`, so any size stays crisp. Names already
+/// taken by other textures are skipped, so fonts sharing an `id` don't overwrite each other.
+///
+/// Sizes are rounded to whole pixels for rasterizing, fractional sizes are scaled from those.
+/// Kerning comes from the `GPOS` table, or the older `kern` table for fonts without one.
+pub struct TrueTypeFont {
+    id: String,
+    font: FontVec,
+    pages: Vec<GlyphPage>,
+    // number in the name of the next page
+    next_page: usize,
+    // keyed by the rasterized size in pixels
+    glyphs: HashMap<(GlyphId, u32), Glyph>,
+    // in font units, shaping a pair is too slow to repeat for every character drawn
+    kerning: RefCell<HashMap<(char, char), f32>>,
+}
+
+impl std::fmt::Debug for TrueTypeFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrueTypeFont")
+            .field("id", &self.id)
+            .field("pages", &self.pages.len())
+            .field("glyphs", &self.glyphs.len())
+            .finish()
+    }
+}
+
+impl TrueTypeFont {
+    /// `id` names the glyph atlas textures.
+    pub fn from_bytes(id: String, bytes: Vec<u8>) -> Result<Self, ab_glyph::InvalidFont> {
+        Ok(Self {
+            id,
+            font: FontVec::try_from_vec(bytes)?,
+            pages: vec![],
+            next_page: 0,
+            glyphs: HashMap::new(),
+            kerning: RefCell::new(HashMap::new()),
+        })
+    }
+
+    fn units_per_em(&self) -> f32 {
+        self.font.units_per_em().unwrap_or(1000.0)
+    }
+
+    /// The font's scale at font size `size`, which like in CSS is the em size rather than the
+    /// line height `ab_glyph` measures in.
+    fn scale(&self, size: f32) -> PxScale {
+        PxScale::from(size * self.font.height_unscaled() / self.units_per_em())
+    }
+
+    /// Removes the atlas textures, glyphs are rasterized again when next drawn. Worth calling
+    /// after drawing text at many sizes that are no longer needed.
+    pub fn clear_cache(&mut self, engine: &mut Engine) {
+        for page in self.pages.drain(..) {
+            engine.remove_texture(page.texture);
+        }
+        self.glyphs.clear();
+    }
+
+    fn rasterize(&mut self, engine: &mut Engine, glyph_id: GlyphId, pixel_size: u32) -> Glyph {
+        let scale = self.scale(pixel_size as f32);
+        let scaled_font = self.font.as_scaled(scale);
+        let mut glyph = Glyph {
+            region: None,
+            offset: Vec2::ZERO,
+            size: Vec2::ZERO,
+            advance: scaled_font.h_advance(glyph_id),
+        };
+        let Some(outline) = self.font.outline_glyph(glyph_id.with_scale(scale)) else {
+            // nothing to draw, like a space
+            return glyph;
+        };
+
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        let mut rgba = vec![255; (width * height * 4) as usize];
+        outline.draw(|x, y, coverage| {
+            rgba[((y * width + x) * 4 + 3) as usize] = (coverage * 255.0).round() as u8;
+        });
+
+        let Some((texture, x, y)) = self.allocate(engine, width, height) else {
+            log::warn!(
+                "Glyph {:?} at {}px doesn't fit in a {}x{} glyph page",
+                glyph_id,
+                pixel_size,
+                PAGE_SIZE,
+                PAGE_SIZE
+            );
+            return glyph;
+        };
+        let rect = RegionRect::Pixels {
+            x,
+            y,
+            width,
+            height,
+        };
+        // the page was just created with room for this rect
+        engine.update_texture(texture, rect, &rgba).unwrap();
+
+        glyph.region = Some(TextureRegion { texture, rect });
+        glyph.offset = Vec2::new(bounds.min.x, bounds.min.y);
+        glyph.size = Vec2::new(width as f32, height as f32);
+        glyph
+    }
+
+    /// Finds room for a `width`x`height` glyph, adding a page if every other one is full.
+    fn allocate(
+        &mut self,
+        engine: &mut Engine,
+        width: u32,
+        height: u32,
+    ) -> Option<(TextureHandle, u32, u32)> {
+        for page in &mut self.pages {
+            if let Some((x, y)) = page.packer.pack(width, height) {
+                return Some((page.texture, x, y));
+            }
+        }
+
+        let page_size = PAGE_SIZE.min(engine.max_texture_dimension());
+        let mut packer = ShelfPacker::new(page_size, page_size, GLYPH_PADDING);
+        let (x, y) = packer.pack(width, height)?;
+        // creating a texture with a name in use would replace that texture
+        let name = loop {
+            let name = format!("{}-glyphs-{}", self.id, self.next_page);
+            self.next_page += 1;
+            if engine.textures.handle(&name).is_none() {
+                break name;
+            }
+        };
+        let texture = match engine.create_dynamic_texture(name, page_size, page_size) {
+            Ok(texture) => texture,
+            Err(err) => {
+                log::warn!("Couldn't create a glyph page: {}", err);
+                return None;
+            }
+        };
+        self.pages.push(GlyphPage { texture, packer });
+        Some((texture, x, y))
+    }
+}
+
+impl Font for TrueTypeFont {
+    fn line_metrics(&self, size: f32) -> LineMetrics {
+        let scaled_font = self.font.as_scaled(self.scale(size));
+        LineMetrics {
+            ascent: scaled_font.ascent(),
+            descent: -scaled_font.descent(),
+            line_height: scaled_font.height() + scaled_font.line_gap(),
+        }
+    }
+
+    fn glyph(&mut self, engine: &mut Engine, ch: char, size: f32) -> Option<Glyph> {
+        let glyph_id = self.font.glyph_id(ch);
+        let pixel_size = size.round().max(1.0) as u32;
+        let glyph = match self.glyphs.get(&(glyph_id, pixel_size)) {
+            Some(glyph) => *glyph,
+            None => {
+                let glyph = self.rasterize(engine, glyph_id, pixel_size);
+                self.glyphs.insert((glyph_id, pixel_size), glyph);
+                glyph
+            }
+        };
+        Some(glyph.scaled(size / pixel_size as f32))
+    }
+
+    fn kerning(&self, left: char, right: char, size: f32) -> f32 {
+        let kerning = *self
+            .kerning
+            .borrow_mut()
+            .entry((left, right))
+            .or_insert_with(|| shaped_kerning(self.font.as_slice(), left, right));
+        kerning * size / self.units_per_em()
+    }
+}
+
+/// How much shaping `left` and `right` together moves `right`, in font units. Goes through the
+/// whole `GPOS` table, which `ab_glyph` doesn't read.
+fn shaped_kerning(font_data: &[u8], left: char, right: char) -> f32 {
+    let Some(face) = rustybuzz::Face::from_slice(font_data, 0) else {
+        return 0.0;
+    };
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(&format!("{left}{right}"));
+    let shaped = rustybuzz::shape(&face, &[], buffer);
+    // a ligature or some other substitution, there's no pair to kern
+    let [left_info, _] = shaped.glyph_infos() else {
+        return 0.0;
+    };
+    let [left_position, right_position] = shaped.glyph_positions() else {
+        return 0.0;
+    };
+    let advance = face
+        .glyph_hor_advance(rustybuzz::ttf_parser::GlyphId(left_info.glyph_id as u16))
+        .unwrap_or(0);
+    (left_position.x_advance - advance as i32 + right_position.x_offset) as f32
+}
+
+impl Engine {
+    /// Loads a `.ttf` or `.otf` font, see `TrueTypeFont`.
+    pub fn load_font(
+        &mut self,
+        id: String,
+        path: impl AsRef<Path>,
+    ) -> Result<TrueTypeFont, EngineError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|source| EngineError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        TrueTypeFont::from_bytes(id, bytes).map_err(|err| EngineError::InvalidFont {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        })
+    }
+}